@@ -0,0 +1,32 @@
+use std::time::Instant;
+
+use advent2023::day04::{sum_of_all_cards_points, total_cards, CardIds, Doubling, Linear, Overflow};
+
+const CARDS: u64 = 200;
+
+/// Builds a deck where every card has `size` winning numbers and `size` numbers, sharing about one number.
+fn deck(size: u64) -> String {
+    let mut seed = 12345u64;
+    let mut next = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        seed >> 33
+    };
+    (1..=CARDS)
+        .map(|id| {
+            let winners: Vec<String> = (0..size).map(|_| (next() % (size * size)).to_string()).collect();
+            let numbers: Vec<String> = (0..size).map(|_| (next() % (size * size)).to_string()).collect();
+            format!("Card {}: {} | {}", id, winners.join(" "), numbers.join(" "))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn main() {
+    for size in [10, 1_000, 5_000] {
+        let input = deck(size);
+        let start = Instant::now();
+        let points = sum_of_all_cards_points(&input, CardIds::Sequential, &Doubling).unwrap();
+        let total = total_cards(&input, CardIds::Sequential, Overflow::Clamp, &Linear).unwrap();
+        println!("{} cards of {} numbers: {} points, {} cards in {:?}", CARDS, size, points, total, start.elapsed());
+    }
+}
@@ -1,6 +1,10 @@
+use std::fmt;
 use std::mem;
 
+use anyhow::{anyhow, Context, Result};
+
 const GEAR: u8 = b'*';
+const ANSI_RESET: &str = "\x1b[0m";
 
 struct Position {
     x: i64,
@@ -32,6 +36,14 @@ impl Part {
             positions: vec![],
         }
     }
+
+    fn near(&self, position: &Position) -> bool {
+        self.positions.iter().any(|pos| pos.near(position))
+    }
+
+    fn near_any(&self, symbols: &[Symbol]) -> bool {
+        symbols.iter().any(|symbol| self.near(&symbol.position))
+    }
 }
 
 struct Symbol {
@@ -46,58 +58,470 @@ impl Symbol {
             position: Position::new(x, y),
         }
     }
+
+    fn adjacent_parts<'a>(&self, parts: &'a [Part]) -> Vec<&'a Part> {
+        parts.iter().filter(|part| part.near(&self.position)).collect()
+    }
+
+    fn is_gear(&self, parts: &[Part]) -> bool {
+        self.kind == GEAR && self.adjacent_parts(parts).len() == 2
+    }
+}
+
+/// How rows shorter than the rest of the schematic are handled: padded out with '.' or rejected.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RaggedRows {
+    Pad,
+    Error,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RenderStyle {
+    Ansi,
+    Plain,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Cell {
+    Blank,
+    CountedPart,
+    UncountedPart,
+    Symbol,
+    Gear,
+}
+
+impl Cell {
+    fn ansi_colour(&self) -> Option<&'static str> {
+        match self {
+            Cell::Blank => None,
+            Cell::CountedPart => Some("\x1b[32m"),
+            Cell::UncountedPart => Some("\x1b[31m"),
+            Cell::Symbol => Some("\x1b[33m"),
+            Cell::Gear => Some("\x1b[1;35m"),
+        }
+    }
+
+    fn marker(&self) -> u8 {
+        match self {
+            Cell::Blank => b' ',
+            Cell::CountedPart => b'+',
+            Cell::UncountedPart => b'-',
+            Cell::Symbol => b'^',
+            Cell::Gear => b'G',
+        }
+    }
 }
 
-fn load_things(input: &str) -> (Vec<Part>, Vec<Symbol>) {
-    let data: Vec<&[u8]> = input.split('\n').map(|l| l.as_bytes()).collect();
+fn load_rows(input: &str, ragged: RaggedRows) -> Result<Vec<Vec<u8>>> {
+    let mut rows: Vec<Vec<u8>> = input.lines().map(|l| l.trim_end_matches('\r').as_bytes().to_vec()).collect();
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    match ragged {
+        RaggedRows::Pad => {
+            let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+            rows.iter_mut().for_each(|row| row.resize(width, b'.'));
+        }
+        RaggedRows::Error => {
+            if let Some(width) = rows.first().map(Vec::len) {
+                if let Some((y, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != width) {
+                    Err(anyhow!("row {} has length {}, expected {}", y + 1, row.len(), width))?;
+                }
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn push_part(parts: &mut Vec<Part>, part: &mut Part) {
+    let part = mem::replace(part, Part::new());
+    if part.number > 0 {
+        parts.push(part);
+    }
+}
+
+fn load_things(rows: &[Vec<u8>]) -> Result<(Vec<Part>, Vec<Symbol>)> {
     let mut parts = vec![];
     let mut symbols = vec![];
     let mut part = Part::new();
-    let y_len = data.len();
-    let x_len = data[0].len();
-    for y in 0..y_len {
-        for x in 0..x_len {
-            match data[y][x] {
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            match cell {
                 b'0'..=b'9' => {
-                    part.number = part.number * 10 + (data[y][x] - b'0') as u64;
+                    part.number = part.number.checked_mul(10)
+                        .and_then(|n| n.checked_add((cell - b'0') as u64))
+                        .with_context(|| format!("part number too large at row {}, column {}", y + 1, x + 1))?;
                     part.positions.push(Position::new(x, y));
                 }
                 _ => {
-                    if data[y][x] != b'.' {
-                        symbols.push(Symbol::new(data[y][x], x, y));
+                    if cell != b'.' {
+                        symbols.push(Symbol::new(cell, x, y));
                     }
-                    if part.number > 0 {
-                        parts.push(mem::replace(&mut part, Part::new()));
+                    push_part(&mut parts, &mut part);
+                }
+            }
+        }
+        push_part(&mut parts, &mut part);
+    }
+    Ok((parts, symbols))
+}
+
+pub fn sum_of_parts_near_symbols(input: &str, ragged: RaggedRows) -> Result<u64> {
+    let (parts, symbols) = load_things(&load_rows(input, ragged)?)?;
+    Ok(parts.iter()
+        .filter(|part| part.near_any(&symbols))
+        .map(|part| part.number)
+        .sum())
+}
+
+pub fn sum_of_gear_ratios(input: &str, ragged: RaggedRows) -> Result<u64> {
+    let (parts, symbols) = load_things(&load_rows(input, ragged)?)?;
+    Ok(symbols.iter()
+        .filter(|symbol| symbol.is_gear(&parts))
+        .map(|symbol| symbol.adjacent_parts(&parts).iter().map(|part| part.number).product::<u64>())
+        .sum())
+}
+
+/// Reprints the schematic showing which parts were counted towards the sum. `Ansi` colours counted parts green,
+/// uncounted parts red, symbols yellow and gears magenta. `Plain` follows each row with a marker row using '+' for
+/// counted parts, '-' for uncounted parts, '^' for symbols and 'G' for gears.
+pub fn render_schematic(input: &str, ragged: RaggedRows, style: RenderStyle) -> Result<String> {
+    let rows = load_rows(input, ragged)?;
+    let (parts, symbols) = load_things(&rows)?;
+    let mut cells: Vec<Vec<Cell>> = rows.iter().map(|row| vec![Cell::Blank; row.len()]).collect();
+    for part in &parts {
+        let cell = if part.near_any(&symbols) { Cell::CountedPart } else { Cell::UncountedPart };
+        for pos in &part.positions {
+            cells[pos.y as usize][pos.x as usize] = cell;
+        }
+    }
+    for symbol in &symbols {
+        let cell = if symbol.is_gear(&parts) { Cell::Gear } else { Cell::Symbol };
+        cells[symbol.position.y as usize][symbol.position.x as usize] = cell;
+    }
+
+    let mut output = vec![];
+    for (row, row_cells) in rows.iter().zip(cells.iter()) {
+        if !output.is_empty() {
+            output.push(b'\n');
+        }
+        match style {
+            RenderStyle::Ansi => {
+                let mut current = Cell::Blank;
+                for (&byte, &cell) in row.iter().zip(row_cells.iter()) {
+                    if cell != current {
+                        // Reset first so that bold from a gear doesn't carry over to what follows it
+                        if current != Cell::Blank {
+                            output.extend_from_slice(ANSI_RESET.as_bytes());
+                        }
+                        if let Some(colour) = cell.ansi_colour() {
+                            output.extend_from_slice(colour.as_bytes());
+                        }
+                        current = cell;
                     }
+                    output.push(byte);
                 }
+                if current != Cell::Blank {
+                    output.extend_from_slice(ANSI_RESET.as_bytes());
+                }
+            }
+            RenderStyle::Plain => {
+                output.extend_from_slice(row);
+                let markers: Vec<u8> = row_cells.iter().map(Cell::marker).collect();
+                let markers = String::from_utf8_lossy(&markers);
+                output.push(b'\n');
+                output.extend_from_slice(markers.trim_end().as_bytes());
+            }
+        }
+    }
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// A node in a [`PartGraph`], indexing into its parts or symbols.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Node {
+    Part(usize),
+    Symbol(usize),
+}
+
+/// A set of parts and symbols linked to each other through adjacency.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Component {
+    pub parts: Vec<usize>,
+    pub symbols: Vec<usize>,
+}
+
+/// Parts and symbols as nodes, with an edge wherever a part is adjacent to a symbol.
+pub struct PartGraph {
+    parts: Vec<Part>,
+    symbols: Vec<Symbol>,
+    part_edges: Vec<Vec<usize>>,
+    symbol_edges: Vec<Vec<usize>>,
+}
+
+impl PartGraph {
+    pub fn new(input: &str, ragged: RaggedRows) -> Result<Self> {
+        let (parts, symbols) = load_things(&load_rows(input, ragged)?)?;
+        let mut part_edges = vec![vec![]; parts.len()];
+        let mut symbol_edges = vec![vec![]; symbols.len()];
+        for (s, symbol) in symbols.iter().enumerate() {
+            for (p, part) in parts.iter().enumerate() {
+                if part.near(&symbol.position) {
+                    part_edges[p].push(s);
+                    symbol_edges[s].push(p);
+                }
+            }
+        }
+        Ok(PartGraph { parts, symbols, part_edges, symbol_edges })
+    }
+
+    pub fn part_count(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn part_number(&self, part: usize) -> u64 {
+        self.parts[part].number
+    }
+
+    pub fn symbol_kind(&self, symbol: usize) -> char {
+        self.symbols[symbol].kind as char
+    }
+
+    /// Indices of the parts adjacent to the given symbol.
+    pub fn parts_of_symbol(&self, symbol: usize) -> &[usize] {
+        &self.symbol_edges[symbol]
+    }
+
+    /// Indices of the symbols adjacent to the given part.
+    pub fn symbols_of_part(&self, part: usize) -> &[usize] {
+        &self.part_edges[part]
+    }
+
+    fn neighbours(&self, node: Node) -> Vec<Node> {
+        match node {
+            Node::Part(p) => self.part_edges[p].iter().map(|&s| Node::Symbol(s)).collect(),
+            Node::Symbol(s) => self.symbol_edges[s].iter().map(|&p| Node::Part(p)).collect(),
+        }
+    }
+
+    /// The component containing the given node.
+    pub fn component_of(&self, node: Node) -> Component {
+        let mut seen_parts = vec![false; self.parts.len()];
+        let mut seen_symbols = vec![false; self.symbols.len()];
+        self.walk(node, &mut seen_parts, &mut seen_symbols)
+    }
+
+    /// Every connected component, including lone parts and lone symbols.
+    pub fn components(&self) -> Vec<Component> {
+        let mut seen_parts = vec![false; self.parts.len()];
+        let mut seen_symbols = vec![false; self.symbols.len()];
+        let mut components = vec![];
+        for p in 0..self.parts.len() {
+            if !seen_parts[p] {
+                components.push(self.walk(Node::Part(p), &mut seen_parts, &mut seen_symbols));
+            }
+        }
+        for s in 0..self.symbols.len() {
+            if !seen_symbols[s] {
+                components.push(self.walk(Node::Symbol(s), &mut seen_parts, &mut seen_symbols));
             }
         }
-        if part.number > 0 {
-            parts.push(mem::replace(&mut part, Part::new()));
+        components
+    }
+
+    fn walk(&self, start: Node, seen_parts: &mut [bool], seen_symbols: &mut [bool]) -> Component {
+        let mut component = Component { parts: vec![], symbols: vec![] };
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            let seen = match node {
+                Node::Part(p) => &mut seen_parts[p],
+                Node::Symbol(s) => &mut seen_symbols[s],
+            };
+            if *seen {
+                continue;
+            }
+            *seen = true;
+            match node {
+                Node::Part(p) => component.parts.push(p),
+                Node::Symbol(s) => component.symbols.push(s),
+            }
+            stack.extend(self.neighbours(node));
         }
+        component.parts.sort_unstable();
+        component.symbols.sort_unstable();
+        component
+    }
+
+    pub fn component_sum(&self, component: &Component) -> u64 {
+        component.parts.iter().map(|&p| self.parts[p].number).sum()
+    }
+
+    /// The part number sum of every component, in the order returned by `components`.
+    pub fn component_sums(&self) -> Vec<u64> {
+        self.components().iter().map(|c| self.component_sum(c)).collect()
     }
-    (parts, symbols)
 }
 
-pub fn sum_of_parts_near_symbols(input: &str) -> u64 {
-    let (parts, symbols) = load_things(input);
-    parts.iter()
-        .filter(|part| part.positions.iter().any(|pos| symbols.iter().any(|symbol| symbol.position.near(pos))))
-        .map(|part| part.number)
-        .sum()
+/// A schematic that can be edited one cell at a time, keeping its part sum and gear ratio sum up to date by only
+/// re-examining the parts and gears around each edited cell.
+pub struct Schematic {
+    rows: Vec<Vec<u8>>,
+    part_sum: u64,
+    gear_ratio_sum: u64,
 }
 
-pub fn sum_of_gear_ratios(input: &str) -> u64 {
-    let (parts, symbols) = load_things(input);
-    symbols.iter()
-        .filter(|symbol| symbol.kind == GEAR)
-        .map(|symbol| parts.iter()
-            .filter(|part| part.positions.iter().any(|position| position.near(&symbol.position)))
+impl Schematic {
+    pub fn new(input: &str, ragged: RaggedRows) -> Result<Self> {
+        let rows = load_rows(input, ragged)?;
+        let (parts, symbols) = load_things(&rows)?;
+        let part_sum = parts.iter().filter(|part| part.near_any(&symbols)).map(|part| part.number).sum();
+        let gear_ratio_sum = symbols.iter()
+            .filter(|symbol| symbol.is_gear(&parts))
+            .map(|symbol| symbol.adjacent_parts(&parts).iter().map(|part| part.number).product::<u64>())
+            .sum();
+        Ok(Schematic { rows, part_sum, gear_ratio_sum })
+    }
+
+    pub fn part_sum(&self) -> u64 {
+        self.part_sum
+    }
+
+    pub fn gear_ratio_sum(&self) -> u64 {
+        self.gear_ratio_sum
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<char> {
+        self.rows.get(y).and_then(|row| row.get(x)).map(|&cell| cell as char)
+    }
+
+    pub fn set_cell(&mut self, x: usize, y: usize, value: char) -> Result<()> {
+        if !value.is_ascii_graphic() {
+            Err(anyhow!("can't place {:?} in a schematic", value))?;
+        }
+        let old = self.cell(x, y).with_context(|| format!("cell ({}, {}) is outside the schematic", x, y))? as u8;
+        let value = value as u8;
+        if old == value {
+            return Ok(());
+        }
+
+        let before_parts = self.parts_around(x, y)?;
+        let before_part_sum = self.counted_sum(&before_parts);
+        let mut gears = self.gears_near(&before_parts, x, y);
+        self.rows[y][x] = value;
+        let after_parts = match self.parts_around(x, y) {
+            Ok(parts) => parts,
+            Err(e) => {
+                self.rows[y][x] = old;
+                return Err(e);
+            }
+        };
+        let after_part_sum = self.counted_sum(&after_parts);
+        gears.extend(self.gears_near(&after_parts, x, y));
+        gears.sort_unstable();
+        gears.dedup();
+        let after_gear_ratio = self.gear_ratios(&gears)?;
+        self.rows[y][x] = old;
+        let before_gear_ratio = self.gear_ratios(&gears)?;
+        self.rows[y][x] = value;
+
+        self.part_sum = self.part_sum - before_part_sum + after_part_sum;
+        self.gear_ratio_sum = self.gear_ratio_sum - before_gear_ratio + after_gear_ratio;
+        Ok(())
+    }
+
+    fn neighbourhood(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        for ny in y.saturating_sub(1)..=(y + 1).min(self.rows.len() - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(self.rows[ny].len().saturating_sub(1)) {
+                if nx < self.rows[ny].len() {
+                    cells.push((nx, ny));
+                }
+            }
+        }
+        cells
+    }
+
+    fn part_at(&self, x: usize, y: usize) -> Result<Part> {
+        let row = &self.rows[y];
+        let start = row[..x].iter().rposition(|c| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+        let mut part = Part::new();
+        for (px, &cell) in row.iter().enumerate().skip(start).take_while(|(_, c)| c.is_ascii_digit()) {
+            part.number = part.number.checked_mul(10)
+                .and_then(|n| n.checked_add((cell - b'0') as u64))
+                .with_context(|| format!("part number too large at row {}, column {}", y + 1, px + 1))?;
+            part.positions.push(Position::new(px, y));
+        }
+        Ok(part)
+    }
+
+    /// Every non-zero part with a cell in the 3x3 block around the given cell.
+    fn parts_around(&self, x: usize, y: usize) -> Result<Vec<Part>> {
+        let mut parts: Vec<Part> = vec![];
+        for (nx, ny) in self.neighbourhood(x, y) {
+            let seen = parts.iter().any(|part| part.positions.iter().any(|pos| pos.x == nx as i64 && pos.y == ny as i64));
+            if self.rows[ny][nx].is_ascii_digit() && !seen {
+                parts.push(self.part_at(nx, ny)?);
+            }
+        }
+        parts.retain(|part| part.number > 0);
+        Ok(parts)
+    }
+
+    fn is_symbol(&self, x: usize, y: usize) -> bool {
+        let cell = self.rows[y][x];
+        cell != b'.' && !cell.is_ascii_digit()
+    }
+
+    fn counted_sum(&self, parts: &[Part]) -> u64 {
+        parts.iter()
+            .filter(|part| part.positions.iter()
+                .any(|pos| self.neighbourhood(pos.x as usize, pos.y as usize).into_iter().any(|(x, y)| self.is_symbol(x, y))))
             .map(|part| part.number)
-            .collect::<Vec<u64>>())
-        .filter(|parts| parts.len() == 2)
-        .map(|parts| parts.iter().product::<u64>())
-        .sum()
+            .sum()
+    }
+
+    /// Positions of gear symbols next to any of the given parts or to the given cell.
+    fn gears_near(&self, parts: &[Part], x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut gears = vec![];
+        let cells = parts.iter().flat_map(|part| part.positions.iter().map(|pos| (pos.x as usize, pos.y as usize)));
+        for (cx, cy) in cells.chain([(x, y)]) {
+            for (nx, ny) in self.neighbourhood(cx, cy) {
+                if self.rows[ny][nx] == GEAR {
+                    gears.push((nx, ny));
+                }
+            }
+        }
+        gears
+    }
+
+    fn gear_ratios(&self, gears: &[(usize, usize)]) -> Result<u64> {
+        let mut total = 0;
+        for &(x, y) in gears {
+            if self.rows[y][x] == GEAR {
+                let parts = self.parts_around(x, y)?;
+                if parts.len() == 2 {
+                    total += parts.iter().map(|part| part.number).product::<u64>();
+                }
+            }
+        }
+        Ok(total)
+    }
+}
+
+impl fmt::Display for Schematic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows.iter().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -116,22 +540,137 @@ mod tests {
                        .664.598..";
 
     #[test]
-    fn example_1() {
-        assert_eq!(sum_of_parts_near_symbols(EXAMPLE), 4361);
+    fn example_1() -> Result<()> {
+        assert_eq!(sum_of_parts_near_symbols(EXAMPLE, RaggedRows::Error)?, 4361);
+        Ok(())
+    }
+
+    #[test]
+    fn example_2() -> Result<()> {
+        assert_eq!(sum_of_gear_ratios(EXAMPLE, RaggedRows::Error)?, 467835);
+        Ok(())
+    }
+
+    #[test]
+    fn crlf_and_trailing_newlines() -> Result<()> {
+        let input = format!("{}\r\n\r\n", EXAMPLE.replace('\n', "\r\n"));
+        assert_eq!(sum_of_parts_near_symbols(&input, RaggedRows::Error)?, 4361);
+        assert_eq!(sum_of_gear_ratios(&input, RaggedRows::Error)?, 467835);
+        Ok(())
+    }
+
+    #[test]
+    fn ragged_rows() -> Result<()> {
+        let input = "467..114\n...*\n..35..633.";
+        assert_eq!(sum_of_parts_near_symbols(input, RaggedRows::Pad)?, 467 + 35);
+        let error = sum_of_parts_near_symbols(input, RaggedRows::Error).unwrap_err();
+        assert_eq!(error.to_string(), "row 2 has length 4, expected 8");
+        Ok(())
     }
 
     #[test]
-    fn example_2() {
-        assert_eq!(sum_of_gear_ratios(EXAMPLE), 467835);
+    fn oversized_part_number() {
+        let error = sum_of_parts_near_symbols("123456789012345678901*", RaggedRows::Error).unwrap_err();
+        assert_eq!(error.to_string(), "part number too large at row 1, column 21");
+    }
+
+    #[test]
+    fn render_plain() -> Result<()> {
+        let rendered = render_schematic(EXAMPLE, RaggedRows::Error, RenderStyle::Plain)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 20);
+        assert_eq!(lines[0], "467..114..");
+        assert_eq!(lines[1], "+++  ---");
+        assert_eq!(lines[3], "   G");
+        assert_eq!(lines[11], "     ^ --");
+        assert_eq!(lines[17], "   ^ G");
+        Ok(())
+    }
+
+    #[test]
+    fn render_ansi() -> Result<()> {
+        let rendered = render_schematic(EXAMPLE, RaggedRows::Error, RenderStyle::Ansi)?;
+        let first = rendered.lines().next().unwrap();
+        assert_eq!(first, "\x1b[32m467\x1b[0m..\x1b[31m114\x1b[0m..");
+        assert_eq!(rendered.lines().count(), 10);
+        let gear = render_schematic("617*58", RaggedRows::Error, RenderStyle::Ansi)?;
+        assert_eq!(gear, "\x1b[32m617\x1b[0m\x1b[1;35m*\x1b[0m\x1b[32m58\x1b[0m");
+        Ok(())
+    }
+
+    #[test]
+    fn graph_components() -> Result<()> {
+        let input = "467..114..\n...*......\n..35*.633.\n......#...";
+        let graph = PartGraph::new(input, RaggedRows::Error)?;
+        assert_eq!(graph.part_count(), 4);
+        assert_eq!(graph.symbol_count(), 3);
+        assert_eq!(graph.parts_of_symbol(0).iter().map(|&p| graph.part_number(p)).collect::<Vec<u64>>(), vec![467, 35]);
+        assert_eq!(graph.parts_of_symbol(1).iter().map(|&p| graph.part_number(p)).collect::<Vec<u64>>(), vec![35]);
+        assert_eq!(graph.symbols_of_part(2), &[0, 1]);
+        let components = graph.components();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0], Component { parts: vec![0, 2], symbols: vec![0, 1] });
+        assert_eq!(components[1], Component { parts: vec![1], symbols: vec![] });
+        assert_eq!(components[2], Component { parts: vec![3], symbols: vec![2] });
+        assert_eq!(graph.component_sums(), vec![502, 114, 633]);
+        assert_eq!(graph.component_of(Node::Symbol(1)), components[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn graph_sums_match_part_sum() -> Result<()> {
+        let graph = PartGraph::new(EXAMPLE, RaggedRows::Error)?;
+        let linked: u64 = graph.components().iter()
+            .filter(|c| !c.symbols.is_empty())
+            .map(|c| graph.component_sum(c))
+            .sum();
+        assert_eq!(linked, 4361);
+        Ok(())
+    }
+
+    #[test]
+    fn schematic_edits() -> Result<()> {
+        let mut schematic = Schematic::new(EXAMPLE, RaggedRows::Error)?;
+        assert_eq!((schematic.part_sum(), schematic.gear_ratio_sum()), (4361, 467835));
+
+        schematic.set_cell(7, 4, '*')?;
+        assert_eq!((schematic.part_sum(), schematic.gear_ratio_sum()), (4361 + 58, 467835));
+        schematic.set_cell(5, 1, '7')?;
+        assert_eq!(schematic.cell(5, 1), Some('7'));
+        assert_eq!((schematic.part_sum(), schematic.gear_ratio_sum()), (4361 + 58, 467835));
+        schematic.set_cell(3, 4, '.')?;
+        assert_eq!(schematic.part_sum(), 4361 + 58 - 617);
+
+        assert!(schematic.set_cell(10, 0, '#').is_err());
+        assert!(schematic.set_cell(0, 0, '\n').is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn schematic_edits_match_full_reload() -> Result<()> {
+        let mut schematic = Schematic::new(EXAMPLE, RaggedRows::Error)?;
+        let values = ['.', '*', '#', '1', '5', '9', '*', '.', '0'];
+        let mut seed = 17usize;
+        for _ in 0..500 {
+            seed = (seed * 1103515245 + 12345) % 2147483648;
+            let (x, y, value) = (seed % 10, (seed / 10) % 10, values[(seed / 100) % values.len()]);
+            schematic.set_cell(x, y, value)?;
+            let reloaded = Schematic::new(&schematic.to_string(), RaggedRows::Error)?;
+            assert_eq!(schematic.part_sum(), reloaded.part_sum(), "after setting ({}, {}) to {}", x, y, value);
+            assert_eq!(schematic.gear_ratio_sum(), reloaded.gear_ratio_sum(), "after setting ({}, {}) to {}", x, y, value);
+        }
+        Ok(())
     }
 
     #[test]
-    fn part_1() {
-        println!("Part 1: {}", sum_of_parts_near_symbols(include_str!("../res/day03.txt")));
+    fn part_1() -> Result<()> {
+        println!("Part 1: {}", sum_of_parts_near_symbols(include_str!("../res/day03.txt"), RaggedRows::Error)?);
+        Ok(())
     }
 
     #[test]
-    fn part_2() {
-        println!("Part 2: {}", sum_of_gear_ratios(include_str!("../res/day03.txt")));
+    fn part_2() -> Result<()> {
+        println!("Part 2: {}", sum_of_gear_ratios(include_str!("../res/day03.txt"), RaggedRows::Error)?);
+        Ok(())
     }
 }
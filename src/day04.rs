@@ -1,48 +1,291 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{anyhow, Context, Result};
+
+/// How card IDs are checked when loading. `Sequential` requires every card to follow on from the one before it,
+/// `Mapped` accepts any unique IDs in any order. Either way a card's copies are of the cards after it in the table,
+/// whatever their IDs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CardIds {
+    Sequential,
+    Mapped,
+}
+
+/// What happens when a card wins copies of cards past the end of the table.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Overflow {
+    /// Copies past the end are dropped.
+    Clamp,
+    /// Copies past the end carry on from the start of the table. They add to the total but are never scratched, so
+    /// don't win any further copies, even when they land on cards still to be scratched.
+    Wrap,
+    /// Winning copies past the end is an error.
+    Error,
+    /// The table is extended with virtual cards holding the copies, up to `MAX_VIRTUAL_CARDS` of them. Virtual cards
+    /// are never scratched, so never win copies themselves.
+    Extend,
+}
+
+/// The most virtual cards `Overflow::Extend` will add to the table.
+pub const MAX_VIRTUAL_CARDS: u64 = 1 << 16;
+
 struct Card {
-    winners: Vec<u64>,
+    id: u64,
+    winners: HashSet<u64>,
     numbers: Vec<u64>,
+    /// Copies that get scratched, winning copies of other cards.
     count: u64,
+    /// Copies won by wrapping past the end of the table, which count towards the total but are never scratched.
+    wrapped: u64,
 }
 
 impl Card {
-    fn new(winners: Vec<u64>, numbers: Vec<u64>) -> Self {
-        Self { winners, numbers, count: 1 }
+    fn new(id: u64, winners: HashSet<u64>, numbers: Vec<u64>) -> Self {
+        Self { id, winners, numbers, count: 1, wrapped: 0 }
+    }
+
+    fn add_copies(&mut self, copies: u64) -> Result<()> {
+        self.count = self.count.checked_add(copies).with_context(|| format!("copies of card {} overflow", self.id))?;
+        Ok(())
+    }
+
+    fn add_wrapped(&mut self, copies: u64) -> Result<()> {
+        self.wrapped = self.wrapped.checked_add(copies).with_context(|| format!("copies of card {} overflow", self.id))?;
+        Ok(())
+    }
+
+    fn copies(&self) -> Result<u64> {
+        self.count.checked_add(self.wrapped).with_context(|| format!("copies of card {} overflow", self.id))
     }
 
     fn winning_count(&self) -> u32 {
-        self.numbers.iter().filter(|n| self.winners.contains(n)).count() as u32
+        self.numbers.iter().filter(|n| self.winners.contains(*n)).count() as u32
+    }
+
+    /// Whether each of the card's numbers, in order, is one of its winners.
+    fn matches(&self) -> Vec<bool> {
+        self.numbers.iter().map(|n| self.winners.contains(n)).collect()
+    }
+}
+
+/// Turns the numbers matched on a card into a score, used both for points and for how many copies a card wins.
+pub trait Scoring {
+    /// `matches` holds whether each of the card's numbers, in order, is one of its winners.
+    fn score(&self, matches: &[bool]) -> u64;
+}
+
+fn match_count(matches: &[bool]) -> u64 {
+    matches.iter().filter(|&&m| m).count() as u64
+}
+
+/// The standard scoring for points, used unless a game says otherwise.
+pub const DEFAULT_SCORING: &dyn Scoring = &Doubling;
+
+/// 1 for the first match, doubled for each match after that.
+pub struct Doubling;
+
+impl Scoring for Doubling {
+    fn score(&self, matches: &[bool]) -> u64 {
+        match match_count(matches) {
+            0 => 0,
+            w => 2u64.saturating_pow(w as u32 - 1),
+        }
+    }
+}
+
+/// 1 for each match, which is also the number of copies won in the standard game.
+pub struct Linear;
+
+impl Scoring for Linear {
+    fn score(&self, matches: &[bool]) -> u64 {
+        match_count(matches)
+    }
+}
+
+/// 1, 2, 3, 5, 8, ... for one, two, three, four, five, ... matches.
+pub struct Fibonacci;
+
+impl Scoring for Fibonacci {
+    fn score(&self, matches: &[bool]) -> u64 {
+        let (mut a, mut b) = (0u64, 1u64);
+        for _ in 0..match_count(matches) {
+            (a, b) = (b, a.saturating_add(b));
+        }
+        if a == 0 { 0 } else { b }
+    }
+}
+
+/// Looks up the score by number of matches, with counts past the end of the table using its last entry.
+pub struct Table(pub Vec<u64>);
+
+impl Scoring for Table {
+    fn score(&self, matches: &[bool]) -> u64 {
+        let w = match_count(matches) as usize;
+        self.0.get(w).or(self.0.last()).copied().unwrap_or(0)
+    }
+}
+
+/// 1 for each match, plus `bonus` for each match that directly follows another match.
+pub struct ConsecutiveBonus {
+    pub bonus: u64,
+}
+
+impl Scoring for ConsecutiveBonus {
+    fn score(&self, matches: &[bool]) -> u64 {
+        let streaks = matches.windows(2).filter(|pair| pair[0] && pair[1]).count() as u64;
+        match_count(matches) + streaks * self.bonus
+    }
+}
+
+/// The outcome for a single card once all copies have been won.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CardReport {
+    pub id: u64,
+    pub winning_count: u32,
+    pub points: u64,
+    pub copies: u64,
+}
+
+/// One step of the cascade: the scratched copies of card `from` won copies of card `to`, adding `copies` copies to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CopyStep {
+    pub from: u64,
+    pub to: u64,
+    pub copies: u64,
+}
+
+pub struct CascadeReport {
+    pub cards: Vec<CardReport>,
+    pub trace: Vec<CopyStep>,
+}
+
+impl CascadeReport {
+    pub fn total_cards(&self) -> u64 {
+        self.cards.iter().map(|card| card.copies).sum()
+    }
+
+    /// How many cards ended up with each number of copies.
+    pub fn copy_distribution(&self) -> BTreeMap<u64, usize> {
+        let mut distribution = BTreeMap::new();
+        for card in &self.cards {
+            *distribution.entry(card.copies).or_insert(0) += 1;
+        }
+        distribution
+    }
+}
+
+impl TryFrom<&str> for Card {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        fn parse_numbers(numbers_str: &str) -> Result<Vec<u64>> {
+            numbers_str.split_whitespace()
+                .map(|n| n.parse::<u64>().with_context(|| format!("Couldn't parse number ({})", n)))
+                .collect()
+        }
+
+        let (card_str, rest) = value.split_once(':').with_context(|| format!("card line missing ':' ({})", value))?;
+        let id_str = card_str.strip_prefix("Card").with_context(|| format!("card line missing 'Card' ({})", card_str))?;
+        let id = id_str.trim().parse::<u64>().with_context(|| format!("Couldn't parse card number ({})", card_str))?;
+        let (winners_str, numbers_str) = rest.split_once('|').with_context(|| format!("card line missing '|' ({})", value))?;
+        Ok(Self::new(id, parse_numbers(winners_str)?.into_iter().collect(), parse_numbers(numbers_str)?))
     }
 }
 
-impl From<&str> for Card {
-    fn from(value: &str) -> Self {
-        let (_, rest) = value.split_once(':').unwrap();
-        let (winners_str, numbers_str) = rest.split_once('|').unwrap();
-        Self::new(winners_str.split_whitespace().map(|w| w.parse::<u64>().unwrap()).collect(),
-                  numbers_str.split_whitespace().map(|n| n.parse::<u64>().unwrap()).collect())
+fn load_cards(input: &str, ids: CardIds) -> Result<Vec<Card>> {
+    let mut cards: Vec<Card> = vec![];
+    let mut seen = HashSet::new();
+    for (i, line) in input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let card = Card::try_from(line).with_context(|| format!("line {}", i + 1))?;
+        match cards.last() {
+            Some(previous) if ids == CardIds::Sequential && card.id != previous.id + 1 =>
+                Err(anyhow!("line {}: card {} doesn't follow card {}", i + 1, card.id, previous.id))?,
+            _ => {}
+        }
+        if !seen.insert(card.id) {
+            Err(anyhow!("line {}: card {} appears more than once", i + 1, card.id))?;
+        }
+        cards.push(card);
     }
+    Ok(cards)
+}
+
+pub fn sum_of_all_cards_points(input: &str, ids: CardIds, scoring: &dyn Scoring) -> Result<u64> {
+    Ok(load_cards(input, ids)?.iter().map(|card| scoring.score(&card.matches())).sum())
 }
 
-fn load_cards(input: &str) -> Vec<Card> {
-    input.split('\n').map(Card::from).collect()
+/// `copies` decides how many of the following cards each card wins a copy of, `Linear` for the standard game.
+pub fn total_cards(input: &str, ids: CardIds, overflow: Overflow, copies: &dyn Scoring) -> Result<u64> {
+    let mut cards = load_cards(input, ids)?;
+    cascade(&mut cards, overflow, copies)?;
+    total(&cards)
 }
 
-pub fn sum_of_all_cards_points(input: &str) -> u64 {
-    load_cards(input).iter()
-        .map(Card::winning_count)
-        .map(|w| if w == 0 { 0 } else { 2u64.pow(w - 1) })
-        .sum()
+pub fn cascade_report(input: &str, ids: CardIds, overflow: Overflow, points: &dyn Scoring, copies: &dyn Scoring) -> Result<CascadeReport> {
+    let mut cards = load_cards(input, ids)?;
+    let trace = cascade(&mut cards, overflow, copies)?;
+    total(&cards)?;
+    let cards = cards.iter()
+        .map(|card| Ok(CardReport { id: card.id, winning_count: card.winning_count(), points: points.score(&card.matches()), copies: card.copies()? }))
+        .collect::<Result<Vec<CardReport>>>()?;
+    Ok(CascadeReport { cards, trace })
 }
 
-pub fn total_cards(input: &str) -> u64 {
-    let mut cards = load_cards(input);
-    for i in 0..cards.len() {
-        let winners = cards[i].winning_count() as usize;
-        for j in 1..=winners {
-            cards[i + j].count += cards[i].count;
+fn total(cards: &[Card]) -> Result<u64> {
+    cards.iter().try_fold(0u64, |total, card| total.checked_add(card.copies()?).context("total number of cards overflows"))
+}
+
+fn cascade(cards: &mut Vec<Card>, overflow: Overflow, copies: &dyn Scoring) -> Result<Vec<CopyStep>> {
+    let mut trace = vec![];
+    let (table_len, last_id) = (cards.len(), cards.last().map_or(0, |card| card.id));
+    for i in 0..table_len {
+        let winners = copies.score(&cards[i].matches());
+        let (from, scratched) = (cards[i].id, cards[i].count);
+        let following = (table_len - i - 1) as u64;
+        for card in &mut cards[i + 1..=i + winners.min(following) as usize] {
+            card.add_copies(scratched)?;
+            trace.push(CopyStep { from, to: card.id, copies: scratched });
+        }
+        let past = winners.saturating_sub(following);
+        if past == 0 {
+            continue;
+        }
+        match overflow {
+            Overflow::Clamp => {}
+            Overflow::Error => Err(anyhow!("card {} wins a copy of card {}, past the end of the table", from, virtual_id(last_id, 0)?))?,
+            Overflow::Wrap => {
+                // Whole laps of the table give every card the same copies, and what's left carries on from the start
+                let (laps, rest) = (past / table_len as u64, past % table_len as u64);
+                for (j, card) in cards[..table_len.min(past.try_into().unwrap_or(usize::MAX))].iter_mut().enumerate() {
+                    let won = scratched.checked_mul(laps + u64::from((j as u64) < rest))
+                        .with_context(|| format!("copies of card {} overflow", card.id))?;
+                    card.add_wrapped(won)?;
+                    trace.push(CopyStep { from, to: card.id, copies: won });
+                }
+            }
+            Overflow::Extend => {
+                if past > MAX_VIRTUAL_CARDS {
+                    Err(anyhow!("card {} wins {} copies past the end of the table, more than {} virtual cards", from, past, MAX_VIRTUAL_CARDS))?;
+                }
+                for k in 0..past {
+                    let j = table_len + k as usize;
+                    if j == cards.len() {
+                        let mut card = Card::new(virtual_id(last_id, k)?, HashSet::new(), vec![]);
+                        card.count = 0;
+                        cards.push(card);
+                    }
+                    cards[j].add_copies(scratched)?;
+                    trace.push(CopyStep { from, to: cards[j].id, copies: scratched });
+                }
+            }
         }
     }
-    cards.iter().map(|c| c.count).sum()
+    Ok(trace)
+}
+
+/// Cards past the end of the table are numbered on from the last card.
+fn virtual_id(last_id: u64, past: u64) -> Result<u64> {
+    last_id.checked_add(1).and_then(|id| id.checked_add(past)).context("virtual card past the largest possible card ID")
 }
 
 #[cfg(test)]
@@ -57,22 +300,177 @@ mod tests {
                            Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
 
     #[test]
-    fn example_1() {
-        assert_eq!(sum_of_all_cards_points(EXAMPLE), 13);
+    fn example_1() -> Result<()> {
+        assert_eq!(sum_of_all_cards_points(EXAMPLE, CardIds::Sequential, &Doubling)?, 13);
+        Ok(())
+    }
+
+    #[test]
+    fn example_2() -> Result<()> {
+        assert_eq!(total_cards(EXAMPLE, CardIds::Sequential, Overflow::Error, &Linear)?, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_newline() -> Result<()> {
+        assert_eq!(total_cards(&format!("{}\n", EXAMPLE), CardIds::Sequential, Overflow::Error, &Linear)?, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let error = sum_of_all_cards_points("Card 1: 1 2 | 3\nCard 2: 1 x | 3", CardIds::Sequential, &Doubling).unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 2: Couldn't parse number (x): invalid digit found in string");
+        let error = sum_of_all_cards_points("Card 1: 1 2 | 3\nCard 2: 1 2 3", CardIds::Sequential, &Doubling).unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 2: card line missing '|' (Card 2: 1 2 3)");
+    }
+
+    #[test]
+    fn card_ids() -> Result<()> {
+        let shuffled = "Card 7: 1 | 1\nCard 3: 1 2 | 1 2\nCard 5: 1 | 1\nCard 4: 1 | 2";
+        let error = total_cards(shuffled, CardIds::Sequential, Overflow::Error, &Linear).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: card 3 doesn't follow card 7");
+        // copies go to the cards that follow in the table: 7 wins 3, then 3 wins 5 and 4, then 5 wins 4
+        assert_eq!(total_cards(shuffled, CardIds::Mapped, Overflow::Clamp, &Linear)?, 1 + 2 + 3 + 6);
+        let report = cascade_report(shuffled, CardIds::Mapped, Overflow::Clamp, &Linear, &Linear)?;
+        assert_eq!(report.cards.iter().map(|card| card.id).collect::<Vec<u64>>(), vec![7, 3, 5, 4]);
+        let gapped = "Card 10: 1 2 | 1 2\nCard 11: 1 | 1\nCard 12: 1 | 2\nCard 20: 1 | 3";
+        assert_eq!(total_cards(gapped, CardIds::Mapped, Overflow::Error, &Linear)?, 1 + 2 + 4 + 1);
+        let error = total_cards("Card 1: 1 | 2\nCard 1: 1 | 2", CardIds::Mapped, Overflow::Error, &Linear).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: card 1 appears more than once");
+        let error = total_cards("Card 10: 1 2 | 1 2\nCard 20: 1 | 3", CardIds::Mapped, Overflow::Error, &Linear).unwrap_err();
+        assert_eq!(error.to_string(), "card 10 wins a copy of card 21, past the end of the table");
+        Ok(())
+    }
+
+    const OVERFLOWING: &str = "Card 1: 1 | 1\nCard 2: 5 | 1\nCard 3: 1 2 | 1 2";
+
+    fn cascaded_counts(overflow: Overflow) -> Result<Vec<(u64, u64)>> {
+        let mut cards = load_cards(OVERFLOWING, CardIds::Sequential)?;
+        cascade(&mut cards, overflow, &Linear)?;
+        cards.iter().map(|card| Ok((card.id, card.copies()?))).collect()
+    }
+
+    #[test]
+    fn overflow_clamp() -> Result<()> {
+        assert_eq!(cascaded_counts(Overflow::Clamp)?, vec![(1, 1), (2, 2), (3, 1)]);
+        assert_eq!(total_cards(OVERFLOWING, CardIds::Sequential, Overflow::Clamp, &Linear)?, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_wrap() -> Result<()> {
+        assert_eq!(cascaded_counts(Overflow::Wrap)?, vec![(1, 2), (2, 3), (3, 1)]);
+        assert_eq!(total_cards(OVERFLOWING, CardIds::Sequential, Overflow::Wrap, &Linear)?, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_wrap_onto_itself_and_beyond() -> Result<()> {
+        // card 1 wins card 2, then wraps onto itself and card 2 again, without its own copy being scratched again
+        let report = cascade_report("Card 1: 1 2 3 | 1 2 3\nCard 2: 9 | 8", CardIds::Sequential, Overflow::Wrap, &Linear, &Linear)?;
+        assert_eq!(report.cards.iter().map(|card| card.copies).collect::<Vec<u64>>(), vec![2, 3]);
+        assert_eq!(report.trace, vec![
+            CopyStep { from: 1, to: 2, copies: 1 },
+            CopyStep { from: 1, to: 1, copies: 1 },
+            CopyStep { from: 1, to: 2, copies: 1 },
+        ]);
+        // card 2 wins more cards than the table holds, and card 3 only scratches the copy it won directly
+        let input = "Card 1: 1 | 2\nCard 2: 1 2 3 4 5 | 1 2 3 4 5\nCard 3: 1 | 1\nCard 4: 1 | 2";
+        let counts = cascade_report(input, CardIds::Sequential, Overflow::Wrap, &Linear, &Linear)?.cards;
+        assert_eq!(counts.iter().map(|card| card.copies).collect::<Vec<u64>>(), vec![2, 2, 3, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn largest_card_id() -> Result<()> {
+        assert_eq!(total_cards("Card 18446744073709551615: 1 | 2", CardIds::Sequential, Overflow::Clamp, &Linear)?, 1);
+        assert_eq!(total_cards("Card 18446744073709551615: 1 | 1", CardIds::Sequential, Overflow::Wrap, &Linear)?, 2);
+        assert!(total_cards("Card 18446744073709551615: 1 | 1", CardIds::Sequential, Overflow::Extend, &Linear).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_error() {
+        let error = total_cards(OVERFLOWING, CardIds::Sequential, Overflow::Error, &Linear).unwrap_err();
+        assert_eq!(error.to_string(), "card 3 wins a copy of card 4, past the end of the table");
+    }
+
+    #[test]
+    fn overflow_extend() -> Result<()> {
+        assert_eq!(cascaded_counts(Overflow::Extend)?, vec![(1, 1), (2, 2), (3, 1), (4, 1), (5, 1)]);
+        assert_eq!(total_cards(OVERFLOWING, CardIds::Sequential, Overflow::Extend, &Linear)?, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_huge_scores() -> Result<()> {
+        let numbers = (1..=70).map(|n| n.to_string()).collect::<Vec<String>>().join(" ");
+        let input = format!("Card 1: {} | {}\nCard 2: 1 | 2", numbers, numbers);
+        let error = total_cards(&input, CardIds::Sequential, Overflow::Error, &Doubling).unwrap_err();
+        assert_eq!(error.to_string(), "card 1 wins a copy of card 3, past the end of the table");
+        assert_eq!(total_cards(&input, CardIds::Sequential, Overflow::Clamp, &Table(vec![0, 1 << 63]))?, 3);
+        assert_eq!(total_cards(OVERFLOWING, CardIds::Sequential, Overflow::Extend, &Table(vec![1]))?, 1 + 2 + 3 + 3);
+
+        // wrapping counts whole laps of the table at once: 2^40 - 1 copies past card 2, over a table of 2
+        let wins = Table(vec![0, 1 << 40]);
+        assert_eq!(total_cards(&input, CardIds::Sequential, Overflow::Wrap, &wins)?, 2 + (1 << 40));
+        let doubling = (1..=45).map(|n| n.to_string()).collect::<Vec<String>>().join(" ");
+        let doubling = format!("Card 1: {} | {}\nCard 2: 1 | 2", doubling, doubling);
+        assert_eq!(total_cards(&doubling, CardIds::Sequential, Overflow::Wrap, &Doubling)?, 2 + (1 << 44));
+        let error = total_cards(&input, CardIds::Sequential, Overflow::Extend, &wins).unwrap_err();
+        assert_eq!(error.to_string(), format!("card 1 wins {} copies past the end of the table, more than 65536 virtual cards", (1u64 << 40) - 1));
+
+        // every card winning a copy of every card after it doubles the copies each time
+        let chain = (1..=70).map(|id| format!("Card {}: 1 | 1", id)).collect::<Vec<String>>().join("\n");
+        let error = total_cards(&chain, CardIds::Sequential, Overflow::Clamp, &Table(vec![0, 100])).unwrap_err();
+        assert_eq!(error.to_string(), "copies of card 65 overflow");
+        let error = total_cards(&input, CardIds::Sequential, Overflow::Wrap, &Table(vec![0, u64::MAX])).unwrap_err();
+        assert_eq!(error.to_string(), "total number of cards overflows");
+        Ok(())
+    }
+
+    fn scores(scoring: &dyn Scoring) -> Vec<u64> {
+        [vec![], vec![false, true], vec![true, true, false], vec![true, true, true, true], vec![true, false, true, true, true]]
+            .iter()
+            .map(|matches| scoring.score(matches))
+            .collect()
+    }
+
+    #[test]
+    fn scoring_rules() -> Result<()> {
+        assert_eq!(scores(&Doubling), vec![0, 1, 2, 8, 8]);
+        assert_eq!(scores(&Linear), vec![0, 1, 2, 4, 4]);
+        assert_eq!(scores(&Fibonacci), vec![0, 1, 2, 5, 5]);
+        assert_eq!(scores(&Table(vec![0, 10, 20])), vec![0, 10, 20, 20, 20]);
+        assert_eq!(scores(&ConsecutiveBonus { bonus: 3 }), vec![0, 1, 5, 13, 10]);
+        assert_eq!(sum_of_all_cards_points(EXAMPLE, CardIds::Sequential, &Linear)?, 4 + 2 + 2 + 1);
+        assert_eq!(total_cards(EXAMPLE, CardIds::Sequential, Overflow::Clamp, &Table(vec![0, 1]))?, 1 + 2 + 3 + 4 + 5 + 1);
+        Ok(())
     }
 
     #[test]
-    fn example_2() {
-        assert_eq!(total_cards(EXAMPLE), 30);
+    fn report() -> Result<()> {
+        let report = cascade_report(EXAMPLE, CardIds::Sequential, Overflow::Error, &Doubling, &Linear)?;
+        assert_eq!(report.total_cards(), 30);
+        assert_eq!(report.cards[0], CardReport { id: 1, winning_count: 4, points: 8, copies: 1 });
+        assert_eq!(report.cards.iter().map(|card| card.copies).collect::<Vec<u64>>(), vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(report.trace.len(), 4 + 2 + 2 + 1);
+        assert_eq!(report.trace[4], CopyStep { from: 2, to: 3, copies: 2 });
+        assert_eq!(report.trace.iter().filter(|step| step.to == 5).map(|step| step.copies).sum::<u64>(), 13);
+        assert_eq!(report.copy_distribution(), BTreeMap::from([(1, 2), (2, 1), (4, 1), (8, 1), (14, 1)]));
+        Ok(())
     }
 
     #[test]
-    fn part_1() {
-        println!("Part 1: {}", sum_of_all_cards_points(include_str!("../res/day04.txt")));
+    fn part_1() -> Result<()> {
+        println!("Part 1: {}", sum_of_all_cards_points(include_str!("../res/day04.txt"), CardIds::Sequential, DEFAULT_SCORING)?);
+        Ok(())
     }
 
     #[test]
-    fn part_2() {
-        println!("Part 2: {}", total_cards(include_str!("../res/day04.txt")));
+    fn part_2() -> Result<()> {
+        println!("Part 2: {}", total_cards(include_str!("../res/day04.txt"), CardIds::Sequential, Overflow::Error, &Linear)?);
+        Ok(())
     }
 }
@@ -1,178 +1,627 @@
-use std::str::Split;
-
-const MAX_IDS: i64 = 100_000_000_000;
-
-struct Almanac {
-    seed_to_soil: Vec<Mapping>,
-    soil_to_fertilizer: Vec<Mapping>,
-    fertilizer_to_water: Vec<Mapping>,
-    water_to_light: Vec<Mapping>,
-    light_to_temperature: Vec<Mapping>,
-    temperature_to_humidity: Vec<Mapping>,
-    humidity_to_location: Vec<Mapping>,
-}
-
-impl From<&mut Split<'_, &str>> for Almanac {
-    fn from(lines: &mut Split<&str>) -> Self {
-        fn load_mappings(lines: &mut Split<&str>) -> Vec<Mapping> {
-            let mut sparse_mappings = vec![];
-            lines.next();  //skip heading
-            let mut line = lines.next().unwrap();
-            while !line.is_empty() {
-                sparse_mappings.push(Mapping::from(line));
-                line = match lines.next() {
-                    None => break,
-                    Some(line) => line,
-                };
+use std::fmt;
+use std::ops::Range;
+use std::slice;
+
+use anyhow::{anyhow, Context, Result};
+
+/// One "X-to-Y map:" section of an almanac, converting `source` category IDs into `destination` category IDs. IDs
+/// not covered by any mapping map to themselves, so the gaps between mappings run exactly up to `u64::MAX`.
+struct Stage {
+    source: String,
+    destination: String,
+    line: usize,
+    mappings: Vec<Mapping>,
+}
+
+impl Stage {
+    fn new(heading: &str, line: usize) -> Result<Self> {
+        let name = heading.strip_suffix(" map:").with_context(|| format!("map heading missing ' map:' ({})", heading))?;
+        let (source, destination) = name.split_once("-to-").with_context(|| format!("map heading missing '-to-' ({})", heading))?;
+        Ok(Stage {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            line,
+            mappings: vec![],
+        })
+    }
+
+    /// The destination for `source`, and the mapping that sent it there if it wasn't in a gap.
+    fn trace_source(&self, source: u64) -> (u64, Option<&Mapping>) {
+        for map in &self.mappings {
+            if let Some(destination) = map.map(source) {
+                return (destination, Some(map));
+            }
+        }
+        (source, None)
+    }
+
+    fn destination_for_source(&self, source: u64) -> u64 {
+        self.trace_source(source).0
+    }
+
+    /// Splits `range` into the pieces sent to the same place by a single mapping, or left alone by a gap.
+    fn trace_range(&self, range: Range<u64>) -> Vec<TracedRange> {
+        let mut pieces = vec![];
+        let mut cursor = range.start;
+        for m in &self.mappings {
+            let start = m.source_start.max(cursor);
+            let end = (m.source_start + m.range).min(range.end);
+            if start >= end {
+                continue;
             }
-            sparse_mappings.sort_unstable_by_key(|m| m.source_start);
-            let mut mappings = vec![];
-            if sparse_mappings[0].source_start != 0 {
-                mappings.push(Mapping {
-                    destination_start: 0,
-                    source_start: 0,
-                    range: sparse_mappings[0].source_start,
-                })
+            if start > cursor {
+                pieces.push(TracedRange { source: cursor..start, range: cursor..start, line: None });
             }
-            mappings.push(sparse_mappings[0].clone());
-            for (a, b) in sparse_mappings.iter().zip(sparse_mappings.iter().skip(1)) {
-                let gap_start = a.source_start + a.range;
-                if b.source_start != (gap_start) {
-                    mappings.push(Mapping {
-                        destination_start: gap_start,
-                        source_start: gap_start,
-                        range: b.source_start - gap_start,
-                    })
+            let destination = m.destination_start + (start - m.source_start);
+            pieces.push(TracedRange { source: start..end, range: destination..destination + (end - start), line: Some(m.line) });
+            cursor = end;
+        }
+        if cursor < range.end {
+            pieces.push(TracedRange { source: cursor..range.end, range: cursor..range.end, line: None });
+        }
+        pieces
+    }
+
+    /// Every source ID this stage maps to `destination`.
+    fn sources_for_destination(&self, destination: u64) -> Vec<u64> {
+        let mut sources: Vec<u64> = self.mappings.iter()
+            .filter_map(|m| m.unmap(destination))
+            .chain([destination])
+            .filter(|&source| self.destination_for_source(source) == destination)
+            .collect();
+        sources.sort_unstable();
+        sources.dedup();
+        sources
+    }
+
+    fn interval_map(&self) -> IntervalMap {
+        IntervalMap::from(&self.mappings[..])
+    }
+
+    fn name(&self) -> String {
+        format!("{}-to-{}", self.source, self.destination)
+    }
+
+    fn issues(&self) -> Vec<Issue> {
+        let mut issues = vec![];
+        if self.mappings.is_empty() {
+            issues.push(Issue::EmptySection { stage: self.name(), line: self.line });
+        }
+        for m in self.mappings.iter().filter(|m| m.range == 0) {
+            issues.push(Issue::ZeroLength { stage: self.name(), line: m.line });
+        }
+        for (line, other_line) in overlaps(&self.mappings, |m| m.source_start) {
+            issues.push(Issue::OverlappingSources { stage: self.name(), line, other_line });
+        }
+        for (line, other_line) in overlaps(&self.mappings, |m| m.destination_start) {
+            issues.push(Issue::CollidingDestinations { stage: self.name(), line, other_line });
+        }
+        let unmapped = complement(&merge_ranges(self.mappings.iter().map(|m| m.source_start..m.source_start + m.range).collect()));
+        for m in &self.mappings {
+            let destinations = m.destination_start..m.destination_start + m.range;
+            if let Some(ids) = intersect_ranges(slice::from_ref(&destinations), &unmapped).first() {
+                issues.push(Issue::CollidesWithUnmapped { stage: self.name(), line: m.line, ids: ids.clone() });
+            }
+        }
+        issues
+    }
+}
+
+/// Pairs of lines for mappings whose ranges starting at `start` overlap, each paired with the earlier mapping
+/// that reaches furthest past its start.
+fn overlaps(mappings: &[Mapping], start: fn(&Mapping) -> u64) -> Vec<(usize, usize)> {
+    let mut sorted: Vec<&Mapping> = mappings.iter().filter(|m| m.range > 0).collect();
+    sorted.sort_by_key(|&m| (start(m), m.line));
+    let mut overlaps = vec![];
+    let mut furthest: Option<(u64, usize)> = None;
+    for m in sorted {
+        let end = start(m) + m.range;
+        match furthest {
+            Some((furthest_end, line)) if start(m) < furthest_end => {
+                overlaps.push((m.line, line));
+                if end > furthest_end {
+                    furthest = Some((end, m.line));
                 }
-                mappings.push(b.clone());
             }
-            let last = mappings.len() - 1;
-            let start = mappings[last].source_start + mappings[last].range;
-            mappings.push(Mapping {
-                destination_start: start,
-                source_start: start,
-                range: MAX_IDS - start,
-            });
-            mappings
+            _ => furthest = Some((end, m.line)),
         }
+    }
+    overlaps.sort_unstable();
+    overlaps
+}
 
-        Almanac {
-            seed_to_soil: load_mappings(lines),
-            soil_to_fertilizer: load_mappings(lines),
-            fertilizer_to_water: load_mappings(lines),
-            water_to_light: load_mappings(lines),
-            light_to_temperature: load_mappings(lines),
-            temperature_to_humidity: load_mappings(lines),
-            humidity_to_location: load_mappings(lines),
+/// Something wrong with an almanac that doesn't stop it being read, but may give wrong answers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Issue {
+    /// The chain of stages doesn't include a category the puzzle needs.
+    MissingCategory { category: String },
+    /// A map heading with no mappings under it.
+    EmptySection { stage: String, line: usize },
+    /// A mapping that covers no IDs.
+    ZeroLength { stage: String, line: usize },
+    /// A mapping covering some of the same source IDs as an earlier one, so only one of them can apply.
+    OverlappingSources { stage: String, line: usize, other_line: usize },
+    /// A mapping sending IDs to some of the same destination IDs as another, so the stage isn't reversible.
+    CollidingDestinations { stage: String, line: usize, other_line: usize },
+    /// A mapping sending IDs to some of the IDs no mapping covers, which already map to themselves.
+    CollidesWithUnmapped { stage: String, line: usize, ids: Range<u64> },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingCategory { category } => write!(f, "almanac has no {} map", category),
+            Issue::EmptySection { stage, line } => write!(f, "line {}: {} map has no mappings", line, stage),
+            Issue::ZeroLength { stage, line } => write!(f, "line {}: {} mapping has zero length", line, stage),
+            Issue::OverlappingSources { stage, line, other_line } =>
+                write!(f, "line {}: {} mapping overlaps the sources of line {}", line, stage, other_line),
+            Issue::CollidingDestinations { stage, line, other_line } =>
+                write!(f, "line {}: {} mapping collides with the destinations of line {}", line, stage, other_line),
+            Issue::CollidesWithUnmapped { stage, line, ids } =>
+                write!(f, "line {}: {} mapping collides with unmapped IDs {}..{}, which map to themselves", line, stage, ids.start, ids.end),
         }
     }
 }
 
+/// The value reached in one category while tracing a single ID, and the line of the mapping that sent it there.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceStep {
+    pub category: String,
+    pub value: u64,
+    pub line: Option<usize>,
+}
+
+/// One piece of a traced range: the IDs `range` that `source` in the previous category was sent to, and the line of
+/// the mapping that sent them if they weren't in a gap.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TracedRange {
+    pub source: Range<u64>,
+    pub range: Range<u64>,
+    pub line: Option<usize>,
+}
+
+/// The pieces a set of ranges has been split into on reaching a category.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RangeTraceStep {
+    pub category: String,
+    pub pieces: Vec<TracedRange>,
+}
+
+pub struct Trace(pub Vec<TraceStep>);
+
+pub struct RangeTrace(pub Vec<RangeTraceStep>);
+
+fn write_table(f: &mut fmt::Formatter<'_>, rows: Vec<Vec<String>>) -> fmt::Result {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns).map(|c| rows.iter().filter_map(|row| row.get(c)).map(String::len).max().unwrap_or(0)).collect();
+    for row in rows {
+        let cells: Vec<String> = row.iter().zip(&widths).enumerate()
+            .map(|(c, (cell, &width))| if c == 0 { format!("{:<width$}", cell) } else { format!("{:>width$}", cell) })
+            .collect();
+        writeln!(f, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+fn line_cell(line: Option<usize>) -> String {
+    line.map_or("-".to_string(), |line| line.to_string())
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heading = vec!["category".to_string(), "value".to_string(), "line".to_string()];
+        write_table(f, [heading].into_iter()
+            .chain(self.0.iter().map(|step| vec![step.category.clone(), step.value.to_string(), line_cell(step.line)]))
+            .collect())
+    }
+}
+
+impl fmt::Display for RangeTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heading = ["category", "start", "end", "line", "from start", "from end"].map(str::to_string).to_vec();
+        write_table(f, [heading].into_iter()
+            .chain(self.0.iter().flat_map(|step| step.pieces.iter().map(|piece| vec![
+                step.category.clone(),
+                piece.range.start.to_string(),
+                piece.range.end.to_string(),
+                line_cell(piece.line),
+                piece.source.start.to_string(),
+                piece.source.end.to_string(),
+            ])))
+            .collect())
+    }
+}
+
+/// Whether almanacs with issues are still solved (`Lenient`) or refused (`Strict`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Validation {
+    Lenient,
+    Strict,
+}
+
+/// The seeds to plant and the chain of stages converting between categories, parsed from their "X-to-Y map:"
+/// headings. Each stage's source must be the previous stage's destination.
+pub struct Almanac {
+    seeds: Vec<u64>,
+    stages: Vec<Stage>,
+}
+
+impl TryFrom<&str> for Almanac {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        let mut lines = value.lines().enumerate();
+        let (_, seeds_line) = lines.next().with_context(|| "almanac is empty")?;
+        let seeds = seeds_line.strip_prefix("seeds:").with_context(|| format!("line 1: seeds line missing 'seeds:' ({})", seeds_line))?
+            .split_whitespace()
+            .map(|s| s.parse::<u64>().with_context(|| format!("line 1: Couldn't parse seed ({})", s)))
+            .collect::<Result<Vec<u64>>>()?;
+
+        let mut stages: Vec<Stage> = vec![];
+        for (i, line) in lines.filter(|(_, line)| !line.trim().is_empty()) {
+            if line.ends_with("map:") {
+                let stage = Stage::new(line, i + 1).with_context(|| format!("line {}", i + 1))?;
+                if let Some(previous) = stages.last() {
+                    if previous.destination != stage.source {
+                        Err(anyhow!("line {}: {}-to-{} map doesn't follow on from {}-to-{} map",
+                            i + 1, stage.source, stage.destination, previous.source, previous.destination))?;
+                    }
+                }
+                if stages.iter().any(|s| s.source == stage.destination) || stage.source == stage.destination {
+                    Err(anyhow!("line {}: {} category appears more than once", i + 1, stage.destination))?;
+                }
+                stages.push(stage);
+            } else {
+                let stage = stages.last_mut().with_context(|| format!("line {}: mapping before any map heading ({})", i + 1, line))?;
+                let mut mapping = Mapping::try_from(line).with_context(|| format!("line {}", i + 1))?;
+                mapping.line = i + 1;
+                stage.mappings.push(mapping);
+            }
+        }
+        stages.iter_mut().for_each(|stage| stage.mappings.sort_unstable_by_key(|m| m.source_start));
+        Ok(Almanac { seeds, stages })
+    }
+}
+
 impl Almanac {
-    fn destination_for_source(source: i64, maps: &Vec<Mapping>) -> i64 {
-        for map in maps {
-            if let Some(destination) = map.map(source) {
-                return destination;
+    fn load(input: &str, validation: Validation) -> Result<Self> {
+        let almanac = Almanac::try_from(input)?;
+        let issues = almanac.issues(&["seed", "location"]);
+        if validation == Validation::Strict && !issues.is_empty() {
+            let issues: Vec<String> = issues.iter().map(Issue::to_string).collect();
+            Err(anyhow!("almanac is invalid:\n{}", issues.join("\n")))?;
+        }
+        Ok(almanac)
+    }
+
+    /// Everything wrong with the almanac, including any of the `required` categories it doesn't have.
+    pub fn issues(&self, required: &[&str]) -> Vec<Issue> {
+        let categories = self.categories();
+        required.iter()
+            .filter(|category| !categories.contains(category))
+            .map(|category| Issue::MissingCategory { category: category.to_string() })
+            .chain(self.stages.iter().flat_map(Stage::issues))
+            .collect()
+    }
+
+    pub fn seeds(&self) -> &[u64] {
+        &self.seeds
+    }
+
+    /// The seeds read as pairs of start and length, joined into sorted, disjoint ranges so that seeds listed more
+    /// than once are only planted once.
+    pub fn seed_ranges(&self) -> Result<Vec<Range<u64>>> {
+        let ranges = self.seeds.chunks_exact(2)
+            .map(|chunk| chunk[0].checked_add(chunk[1])
+                .map(|end| chunk[0]..end)
+                .with_context(|| format!("seed range runs past the largest ID ({} {})", chunk[0], chunk[1])))
+            .collect::<Result<Vec<Range<u64>>>>()?;
+        Ok(merge_ranges(ranges))
+    }
+
+    /// Every category in order, from the first stage's source to the last stage's destination.
+    pub fn categories(&self) -> Vec<&str> {
+        self.stages.first().map(|s| s.source.as_str()).into_iter()
+            .chain(self.stages.iter().map(|s| s.destination.as_str()))
+            .collect()
+    }
+
+    fn stages_between(&self, from: &str, to: &str) -> Result<&[Stage]> {
+        let categories = self.categories();
+        let start = categories.iter().position(|&c| c == from).with_context(|| format!("no {} category in almanac", from))?;
+        let end = categories.iter().position(|&c| c == to).with_context(|| format!("no {} category in almanac", to))?;
+        if end < start {
+            Err(anyhow!("{} comes before {} in the almanac", to, from))?;
+        }
+        Ok(&self.stages[start..end])
+    }
+
+    /// Maps an ID in the `from` category through every stage up to the `to` category.
+    pub fn map(&self, from: &str, to: &str, value: u64) -> Result<u64> {
+        Ok(self.stages_between(from, to)?.iter().fold(value, |value, stage| stage.destination_for_source(value)))
+    }
+
+    /// The value reached in every category from `from` to `to`, and which mapping line sent it there.
+    pub fn trace(&self, from: &str, to: &str, value: u64) -> Result<Trace> {
+        let stages = self.stages_between(from, to)?;
+        let mut steps = vec![TraceStep { category: from.to_string(), value, line: None }];
+        let mut value = value;
+        for stage in stages {
+            let (destination, mapping) = stage.trace_source(value);
+            value = destination;
+            steps.push(TraceStep { category: stage.destination.clone(), value, line: mapping.map(|m| m.line) });
+        }
+        Ok(Trace(steps))
+    }
+
+    /// How `ranges` split up as they pass through every category from `from` to `to`.
+    pub fn trace_ranges(&self, from: &str, to: &str, ranges: &[Range<u64>]) -> Result<RangeTrace> {
+        let stages = self.stages_between(from, to)?;
+        let pieces = ranges.iter().map(|range| TracedRange { source: range.clone(), range: range.clone(), line: None }).collect();
+        let mut steps = vec![RangeTraceStep { category: from.to_string(), pieces }];
+        for stage in stages {
+            let pieces = steps.last().unwrap().pieces.iter().flat_map(|piece| stage.trace_range(piece.range.clone())).collect();
+            steps.push(RangeTraceStep { category: stage.destination.clone(), pieces });
+        }
+        Ok(RangeTrace(steps))
+    }
+
+    /// Every ID in the `from` category that ends up as `value` in the `to` category.
+    pub fn unmap(&self, from: &str, to: &str, value: u64) -> Result<Vec<u64>> {
+        let mut values = vec![value];
+        for stage in self.stages_between(from, to)?.iter().rev() {
+            values = values.into_iter().flat_map(|v| stage.sources_for_destination(v)).collect();
+            values.sort_unstable();
+            values.dedup();
+        }
+        Ok(values)
+    }
+
+    /// The ranges of IDs in the `from` category that end up within `ranges` in the `to` category.
+    pub fn sources_for(&self, from: &str, to: &str, ranges: &[Range<u64>]) -> Result<Vec<Range<u64>>> {
+        Ok(self.interval_map(from, to)?.preimage(ranges))
+    }
+
+    /// All the stages from the `from` category to the `to` category composed into a single map.
+    pub fn interval_map(&self, from: &str, to: &str) -> Result<IntervalMap> {
+        Ok(self.stages_between(from, to)?.iter().fold(IntervalMap::identity(), |map, stage| map.then(&stage.interval_map())))
+    }
+}
+
+/// A run of IDs `range` that a map sends to the same number of IDs starting at `destination`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Segment {
+    range: Range<u64>,
+    destination: u64,
+}
+
+impl Segment {
+    fn image(&self) -> Range<u64> {
+        self.destination..self.destination + (self.range.end - self.range.start)
+    }
+
+    /// The part of this segment whose image lies within `range`.
+    fn restricted_to_image(&self, range: &Range<u64>) -> Segment {
+        let image = self.image();
+        let start = range.start.clamp(image.start, image.end);
+        let end = range.end.clamp(start, image.end);
+        Segment {
+            range: self.range.start + (start - image.start)..self.range.start + (end - image.start),
+            destination: start,
+        }
+    }
+}
+
+/// A piecewise-linear map over IDs, made of sorted, non-overlapping segments. IDs outside every segment map to
+/// themselves, so only the segments that actually move IDs need to be kept. Segments never reach `u64::MAX`, so it
+/// always maps to itself and the segments and gaps below it cover every other ID.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntervalMap {
+    segments: Vec<Segment>,
+}
+
+impl From<&[Mapping]> for IntervalMap {
+    /// Where mappings overlap, the one starting first wins, as it does when searching the sorted mappings.
+    fn from(mappings: &[Mapping]) -> Self {
+        let mut sorted: Vec<&Mapping> = mappings.iter().collect();
+        sorted.sort_by_key(|m| m.source_start);
+        let mut segments = vec![];
+        let mut covered = 0;
+        for m in sorted {
+            let start = m.source_start.max(covered);
+            let end = m.source_start + m.range;
+            if start < end {
+                segments.push(Segment { range: start..end, destination: m.destination_start + (start - m.source_start) });
+                covered = end;
             }
         }
-        source
+        IntervalMap::simplified(segments)
     }
+}
 
-    fn location_for_seed(&self, seed: i64) -> i64 {
-        let soil = Self::destination_for_source(seed, &self.seed_to_soil);
-        let fertilizer = Self::destination_for_source(soil, &self.soil_to_fertilizer);
-        let water = Self::destination_for_source(fertilizer, &self.fertilizer_to_water);
-        let light = Self::destination_for_source(water, &self.water_to_light);
-        let temperature = Self::destination_for_source(light, &self.light_to_temperature);
-        let humidity = Self::destination_for_source(temperature, &self.temperature_to_humidity);
-        let location = Self::destination_for_source(humidity, &self.humidity_to_location);
-        location
+impl IntervalMap {
+    pub fn identity() -> Self {
+        IntervalMap { segments: vec![] }
     }
 
-    fn destinations_for_source(source_start: i64, source_end: i64, maps: &Vec<Mapping>) -> Vec<(i64, i64)> {
-        let mut dests = vec![];
-        for m in maps {
-            let dest_adj = m.destination_start - m.source_start;
-            let dest_start = source_start.max(m.source_start) + dest_adj;
-            let dest_end = source_end.min(m.source_end()) + dest_adj;
-            if dest_start <= dest_end {
-                dests.push((dest_start, dest_end));
+    /// Drops segments that don't move anything and joins neighbouring segments that continue on from each other.
+    fn simplified(segments: Vec<Segment>) -> Self {
+        let mut simplified: Vec<Segment> = vec![];
+        for segment in segments.into_iter().filter(|s| s.destination != s.range.start && !s.range.is_empty()) {
+            match simplified.last_mut() {
+                Some(last) if last.range.end == segment.range.start && last.image().end == segment.destination => last.range.end = segment.range.end,
+                _ => simplified.push(segment),
             }
         }
-        dests
-    }
-
-    fn seed_paths(&self, seeds: Vec<(i64, i64)>) -> i64 {
-        let mut locations = vec![];
-        for (seed_start, seed_end) in seeds.into_iter().map(|(s, r)| (s, s + r - 1)) {
-            for (soil_start, soil_end) in Self::destinations_for_source(seed_start, seed_end, &self.seed_to_soil) {
-                for (fertilizer_start, fertilizer_end) in Self::destinations_for_source(soil_start, soil_end, &self.soil_to_fertilizer) {
-                    for (water_start, water_end) in Self::destinations_for_source(fertilizer_start, fertilizer_end, &self.fertilizer_to_water) {
-                        for (light_start, light_end) in Self::destinations_for_source(water_start, water_end, &self.water_to_light) {
-                            for (temperature_start, temperature_end) in Self::destinations_for_source(light_start, light_end, &self.light_to_temperature) {
-                                for (humidity_start, humidity_end) in Self::destinations_for_source(temperature_start, temperature_end, &self.temperature_to_humidity) {
-                                    for (location_start, _location_end) in Self::destinations_for_source(humidity_start, humidity_end, &self.humidity_to_location) {
-                                        locations.push(location_start);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        IntervalMap { segments: simplified }
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn get(&self, id: u64) -> u64 {
+        let i = self.segments.partition_point(|s| s.range.end <= id);
+        match self.segments.get(i) {
+            Some(segment) if segment.range.start <= id => segment.destination + (id - segment.range.start),
+            _ => id,
+        }
+    }
+
+    /// Splits `range` into the pieces covered by each segment, and the gaps between them mapping to themselves.
+    fn pieces(&self, range: Range<u64>) -> Vec<Segment> {
+        let mut pieces = vec![];
+        let mut cursor = range.start;
+        let first = self.segments.partition_point(|s| s.range.end <= range.start);
+        for segment in self.segments[first..].iter().take_while(|s| s.range.start < range.end) {
+            if segment.range.start > cursor {
+                pieces.push(Segment { range: cursor..segment.range.start, destination: cursor });
+                cursor = segment.range.start;
             }
+            let end = segment.range.end.min(range.end);
+            pieces.push(Segment { range: cursor..end, destination: segment.destination + (cursor - segment.range.start) });
+            cursor = end;
+        }
+        if cursor < range.end {
+            pieces.push(Segment { range: cursor..range.end, destination: cursor });
         }
-        locations.sort_unstable();
-        locations[0]
+        pieces
     }
+
+    /// A single map equivalent to applying this map and then `next`.
+    pub fn then(&self, next: &IntervalMap) -> IntervalMap {
+        let mut segments = vec![];
+        for piece in self.pieces(0..u64::MAX) {
+            for next_piece in next.pieces(piece.image()) {
+                let restricted = piece.restricted_to_image(&next_piece.range);
+                segments.push(Segment { range: restricted.range, destination: next_piece.destination });
+            }
+        }
+        IntervalMap::simplified(segments)
+    }
+
+    /// Maps every ID in `ranges`, returning the resulting IDs as sorted, disjoint ranges.
+    pub fn apply(&self, ranges: &[Range<u64>]) -> Vec<Range<u64>> {
+        merge_ranges(ranges.iter()
+            .flat_map(|range| self.pieces(range.clone()))
+            .map(|piece| piece.image())
+            .collect())
+    }
+
+    /// Every ID that this map sends into `ranges`, as sorted, disjoint ranges.
+    pub fn preimage(&self, ranges: &[Range<u64>]) -> Vec<Range<u64>> {
+        let pieces = self.pieces(0..u64::MAX);
+        merge_ranges(ranges.iter()
+            .flat_map(|range| pieces.iter().map(move |piece| piece.restricted_to_image(range).range))
+            .collect())
+    }
+}
+
+fn intersect_ranges(a: &[Range<u64>], b: &[Range<u64>]) -> Vec<Range<u64>> {
+    merge_ranges(a.iter()
+        .flat_map(|x| b.iter().map(move |y| x.start.max(y.start)..x.end.min(y.end)))
+        .collect())
+}
+
+/// The IDs not in any of the sorted, disjoint `ranges`.
+fn complement(ranges: &[Range<u64>]) -> Vec<Range<u64>> {
+    let mut gaps = vec![];
+    let mut cursor = 0;
+    for range in ranges {
+        gaps.push(cursor..range.start);
+        cursor = range.end;
+    }
+    gaps.push(cursor..u64::MAX);
+    gaps.retain(|gap| !gap.is_empty());
+    gaps
+}
+
+fn merge_ranges(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.retain(|r| !r.is_empty());
+    ranges.sort_unstable_by_key(|r| r.start);
+    let mut merged: Vec<Range<u64>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
 }
 
 #[derive(Clone, Debug)]
 struct Mapping {
-    destination_start: i64,
-    source_start: i64,
-    range: i64,
+    destination_start: u64,
+    source_start: u64,
+    range: u64,
+    line: usize,
 }
 
 impl Mapping {
-    fn map(&self, source: i64) -> Option<i64> {
-        let source_diff = source - self.source_start;
-        if source_diff >= 0 && source_diff < self.range {
-            Some(self.destination_start + source_diff)
-        } else {
-            None
-        }
+    fn map(&self, source: u64) -> Option<u64> {
+        source.checked_sub(self.source_start)
+            .filter(|&source_diff| source_diff < self.range)
+            .and_then(|source_diff| self.destination_start.checked_add(source_diff))
     }
 
-    fn source_end(&self) -> i64 {
-        self.source_start + self.range - 1
+    fn unmap(&self, destination: u64) -> Option<u64> {
+        destination.checked_sub(self.destination_start)
+            .filter(|&destination_diff| destination_diff < self.range)
+            .and_then(|destination_diff| self.source_start.checked_add(destination_diff))
     }
 }
 
-impl From<&str> for Mapping {
-    fn from(value: &str) -> Self {
-        let mut it = value.splitn(3, ' ');
-        Mapping {
-            destination_start: it.next().unwrap().parse::<i64>().unwrap(),
-            source_start: it.next().unwrap().parse::<i64>().unwrap(),
-            range: it.next().unwrap().parse::<i64>().unwrap(),
+impl TryFrom<&str> for Mapping {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        let numbers = value.split_whitespace()
+            .map(|n| n.parse::<u64>().with_context(|| format!("Couldn't parse mapping number ({})", n)))
+            .collect::<Result<Vec<u64>>>()?;
+        match numbers[..] {
+            [destination_start, source_start, range] => {
+                if source_start.checked_add(range).is_none() || destination_start.checked_add(range).is_none() {
+                    Err(anyhow!("mapping runs past the largest ID ({})", value))?;
+                }
+                Ok(Mapping { destination_start, source_start, range, line: 0 })
+            }
+            _ => Err(anyhow!("mapping should have 3 numbers ({})", value)),
         }
     }
 }
 
-pub fn lowest_location_for_individual_seeds(input: &str) -> i64 {
-    let mut lines = input.split("\n");
-    let seeds = lines.next().unwrap()[7..].split_whitespace().map(|s| s.parse::<i64>().unwrap());
-    lines.next(); //skip newline
-    let almanac = Almanac::from(&mut lines);
-    seeds.map(|seed| almanac.location_for_seed(seed)).min().unwrap()
+pub fn lowest_location_for_individual_seeds(input: &str, validation: Validation) -> Result<u64> {
+    let almanac = Almanac::load(input, validation)?;
+    let map = almanac.interval_map("seed", "location")?;
+    almanac.seeds().iter().map(|&seed| map.get(seed)).min().with_context(|| "no seeds to plant")
+}
+
+pub fn lowest_location_for_seed_ranges(input: &str, validation: Validation) -> Result<u64> {
+    reachable_locations(input, validation)?.first().map(|range| range.start).with_context(|| "no seeds to plant")
+}
+
+/// Every location the seed ranges reach, as sorted, disjoint ranges.
+pub fn reachable_locations(input: &str, validation: Validation) -> Result<Vec<Range<u64>>> {
+    let almanac = Almanac::load(input, validation)?;
+    Ok(almanac.interval_map("seed", "location")?.apply(&almanac.seed_ranges()?))
 }
 
-pub fn lowest_location_for_seed_ranges(input: &str) -> i64 {
-    let mut lines = input.split("\n");
-    let seeds: Vec<i64> = lines.next().unwrap()[7..].split_whitespace().map(|s| s.parse::<i64>().unwrap()).collect();
-    let seeds: Vec<(i64, i64)> = seeds.chunks_exact(2).map(|chunk| (chunk[0], chunk[1])).collect();
-    lines.next(); //skip newline
-    Almanac::from(&mut lines).seed_paths(seeds)
+/// The lowest location for the individual seeds, and the seed planted there.
+pub fn lowest_location_and_seed_for_individual_seeds(input: &str, validation: Validation) -> Result<(u64, u64)> {
+    let almanac = Almanac::load(input, validation)?;
+    let map = almanac.interval_map("seed", "location")?;
+    almanac.seeds().iter().map(|&seed| (map.get(seed), seed)).min().with_context(|| "no seeds to plant")
+}
+
+/// The lowest location for the seed ranges, and the lowest seed planted there.
+pub fn lowest_location_and_seed_for_seed_ranges(input: &str, validation: Validation) -> Result<(u64, u64)> {
+    let almanac = Almanac::load(input, validation)?;
+    let seeds = almanac.seed_ranges()?;
+    let map = almanac.interval_map("seed", "location")?;
+    let location = map.apply(&seeds).first().map(|range| range.start).with_context(|| "no seeds to plant")?;
+    let origins = intersect_ranges(&map.preimage(slice::from_ref(&(location..location + 1))), &seeds);
+    let seed = origins.first().map(|range| range.start).with_context(|| format!("no seed reaches location {}", location))?;
+    Ok((location, seed))
 }
 
 #[cfg(test)]
@@ -214,22 +663,216 @@ mod tests {
                            56 93 4";
 
     #[test]
-    fn example_1() {
-        assert_eq!(lowest_location_for_individual_seeds(EXAMPLE), 35);
+    fn example_1() -> Result<()> {
+        assert_eq!(lowest_location_for_individual_seeds(EXAMPLE, Validation::Strict)?, 35);
+        Ok(())
+    }
+
+    #[test]
+    fn example_2() -> Result<()> {
+        assert_eq!(lowest_location_for_seed_ranges(EXAMPLE, Validation::Strict)?, 46);
+        Ok(())
+    }
+
+    #[test]
+    fn named_stages() -> Result<()> {
+        let almanac = Almanac::try_from(EXAMPLE)?;
+        assert_eq!(almanac.categories(), vec!["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"]);
+        assert_eq!(almanac.map("seed", "soil", 79)?, 81);
+        assert_eq!(almanac.map("soil", "water", 81)?, 81);
+        assert_eq!(almanac.map("light", "light", 74)?, 74);
+        assert_eq!(almanac.map("seed", "location", 14)?, 43);
+        assert!(almanac.map("location", "seed", 82).is_err());
+        assert!(almanac.map("seed", "planet", 82).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn short_and_long_chains() -> Result<()> {
+        let short = Almanac::try_from("seeds: 1 5\n\nseed-to-location map:\n10 0 3")?;
+        assert_eq!(short.map("seed", "location", 1)?, 11);
+        assert_eq!(short.map("seed", "location", 5)?, 5);
+        let long = Almanac::try_from("seeds: 1\n\na-to-b map:\n10 0 3\n\nb-to-c map:\n\nc-to-d map:\n0 11 1")?;
+        assert_eq!(long.categories(), vec!["a", "b", "c", "d"]);
+        assert_eq!(long.map("a", "d", 1)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn broken_chains() {
+        let error = Almanac::try_from("seeds: 1\n\nseed-to-soil map:\n1 2 3\n\nwater-to-light map:\n1 2 3").err().unwrap();
+        assert_eq!(error.to_string(), "line 6: water-to-light map doesn't follow on from seed-to-soil map");
+        let error = Almanac::try_from("seeds: 1\n\nseed-to-soil map:\n\nsoil-to-seed map:").err().unwrap();
+        assert_eq!(error.to_string(), "line 5: seed category appears more than once");
+        let error = Almanac::try_from("seeds: 1\n\nseed-to-soil map:\n1 2").err().unwrap();
+        assert_eq!(format!("{:#}", error), "line 4: mapping should have 3 numbers (1 2)");
+    }
+
+    #[test]
+    fn composed_map_matches_stages() -> Result<()> {
+        let almanac = Almanac::try_from(EXAMPLE)?;
+        let map = almanac.interval_map("seed", "location")?;
+        for seed in 0..120 {
+            assert_eq!(map.get(seed), almanac.map("seed", "location", seed)?);
+        }
+        let soil_to_light = almanac.interval_map("soil", "light")?;
+        assert_eq!(soil_to_light.get(81), almanac.map("soil", "light", 81)?);
+        assert_eq!(almanac.interval_map("water", "water")?, IntervalMap::identity());
+        Ok(())
+    }
+
+    #[test]
+    fn interval_maps() {
+        let shift = IntervalMap::from(&[Mapping { destination_start: 10, source_start: 0, range: 5, line: 0 },
+            Mapping { destination_start: 15, source_start: 5, range: 5, line: 0 },
+            Mapping { destination_start: 20, source_start: 20, range: 5, line: 0 }][..]);
+        assert_eq!(shift.segment_count(), 1);
+        let back = IntervalMap::from(&[Mapping { destination_start: 0, source_start: 10, range: 10, line: 0 }][..]);
+        assert_eq!(shift.then(&back).segment_count(), 1);
+        assert_eq!(shift.then(&back).get(3), 3);
+        assert_eq!(shift.then(&back).get(15), 5);
+        assert_eq!(back.then(&shift).get(12), 12);
+        assert_eq!(back.then(&shift).get(3), 13);
+        assert_eq!(shift.apply(&[3..8, 7..12, 30..31]), vec![10..12, 13..20, 30..31]);
+    }
+
+    #[test]
+    fn inverse_mapping() -> Result<()> {
+        let almanac = Almanac::try_from(EXAMPLE)?;
+        assert_eq!(almanac.unmap("seed", "soil", 81)?, vec![79]);
+        assert_eq!(almanac.unmap("seed", "location", 82)?, vec![79]);
+        assert_eq!(almanac.unmap("seed", "soil", 50)?, vec![98]);
+        assert_eq!(almanac.unmap("seed", "soil", 99)?, vec![97]);
+        for seed in 0..120 {
+            let location = almanac.map("seed", "location", seed)?;
+            assert!(almanac.unmap("seed", "location", location)?.contains(&seed));
+            assert_eq!(almanac.sources_for("seed", "location", slice::from_ref(&(location..location + 1)))?.iter().filter(|r| r.contains(&seed)).count(), 1);
+        }
+        let seeds = almanac.sources_for("seed", "location", slice::from_ref(&(46..47)))?;
+        assert!(seeds.iter().any(|range| range.contains(&82)));
+        Ok(())
+    }
+
+    #[test]
+    fn originating_seeds() -> Result<()> {
+        assert_eq!(lowest_location_and_seed_for_individual_seeds(EXAMPLE, Validation::Strict)?, (35, 13));
+        assert_eq!(lowest_location_and_seed_for_seed_ranges(EXAMPLE, Validation::Strict)?, (46, 82));
+        Ok(())
+    }
+
+    #[test]
+    fn full_id_range() -> Result<()> {
+        let almanac = Almanac::try_from("seeds: 200000000005 18446744073709551614\n\nseed-to-location map:\n0 200000000000 10\n18446744073709551600 18446744073709551610 5")?;
+        assert_eq!(almanac.map("seed", "location", 200000000005)?, 5);
+        assert_eq!(almanac.map("seed", "location", 18446744073709551614)?, 18446744073709551604);
+        assert_eq!(almanac.map("seed", "location", u64::MAX)?, u64::MAX);
+        assert_eq!(lowest_location_for_individual_seeds("seeds: 200000000005 18446744073709551614\n\nseed-to-location map:\n0 200000000000 10", Validation::Lenient)?, 5);
+        let map = almanac.interval_map("seed", "location")?;
+        assert_eq!(map.apply(slice::from_ref(&(18446744073709551605..u64::MAX))), vec![18446744073709551600..18446744073709551610]);
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_range_values() {
+        let error = Almanac::try_from("seeds: 1\n\nseed-to-soil map:\n18446744073709551615 0 2").err().unwrap();
+        assert_eq!(format!("{:#}", error), "line 4: mapping runs past the largest ID (18446744073709551615 0 2)");
+        assert!(Almanac::try_from("seeds: 1\n\nseed-to-soil map:\n-1 0 2").is_err());
+        assert!(Almanac::try_from("seeds: -1\n\nseed-to-soil map:\n1 0 2").is_err());
+        let error = lowest_location_for_seed_ranges("seeds: 18446744073709551610 10\n\nseed-to-location map:\n1 0 2", Validation::Lenient).unwrap_err();
+        assert_eq!(error.to_string(), "seed range runs past the largest ID (18446744073709551610 10)");
+    }
+
+    #[test]
+    fn validation() -> Result<()> {
+        assert_eq!(Almanac::try_from(EXAMPLE)?.issues(&["seed", "location"]), vec![]);
+        let input = "seeds: 1 2\n\nseed-to-soil map:\n50 98 2\n52 50 48\n60 90 10\n5 5 0\n\nsoil-to-water map:\n";
+        let almanac = Almanac::try_from(input)?;
+        let issues: Vec<String> = almanac.issues(&["seed", "location"]).iter().map(Issue::to_string).collect();
+        assert_eq!(issues, vec![
+            "almanac has no location map",
+            "line 7: seed-to-soil mapping has zero length",
+            "line 4: seed-to-soil mapping overlaps the sources of line 6",
+            "line 6: seed-to-soil mapping overlaps the sources of line 5",
+            "line 6: seed-to-soil mapping collides with the destinations of line 5",
+            "line 9: soil-to-water map has no mappings",
+        ]);
+        assert_eq!(lowest_location_for_individual_seeds(&input.replace("water", "location"), Validation::Lenient)?, 1);
+        let error = lowest_location_for_individual_seeds(&input.replace("water", "location"), Validation::Strict).unwrap_err();
+        assert!(error.to_string().starts_with("almanac is invalid:\nline 7: seed-to-soil mapping has zero length"));
+
+        let almanac = Almanac::try_from("seeds: 2\n\nseed-to-location map:\n0 10 5\n")?;
+        assert_eq!(almanac.unmap("seed", "location", 2)?, vec![2, 12]);
+        let issues: Vec<String> = almanac.issues(&["seed", "location"]).iter().map(Issue::to_string).collect();
+        assert_eq!(issues, vec!["line 4: seed-to-location mapping collides with unmapped IDs 0..5, which map to themselves"]);
+        Ok(())
+    }
+
+    #[test]
+    fn seed_trace() -> Result<()> {
+        let almanac = Almanac::try_from(EXAMPLE)?;
+        let trace = almanac.trace("seed", "location", 79)?;
+        let values: Vec<(&str, u64, Option<usize>)> = trace.0.iter().map(|s| (s.category.as_str(), s.value, s.line)).collect();
+        assert_eq!(values, vec![("seed", 79, None), ("soil", 81, Some(5)), ("fertilizer", 81, None), ("water", 81, None),
+                                ("light", 74, Some(20)), ("temperature", 78, Some(25)), ("humidity", 78, None), ("location", 82, Some(32))]);
+        let table = trace.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "category     value  line");
+        assert_eq!(lines[1], "seed            79     -");
+        assert_eq!(lines[8], "location        82    32");
+        Ok(())
+    }
+
+    #[test]
+    fn range_trace() -> Result<()> {
+        let almanac = Almanac::try_from(EXAMPLE)?;
+        let trace = almanac.trace_ranges("seed", "fertilizer", slice::from_ref(&(95..100)))?;
+        assert_eq!(trace.0[1].pieces, vec![
+            TracedRange { source: 95..98, range: 97..100, line: Some(5) },
+            TracedRange { source: 98..100, range: 50..52, line: Some(4) },
+        ]);
+        assert_eq!(trace.0[2].pieces, vec![
+            TracedRange { source: 97..100, range: 97..100, line: None },
+            TracedRange { source: 50..52, range: 35..37, line: Some(8) },
+        ]);
+        let table = trace.to_string();
+        assert_eq!(table.lines().next(), Some("category    start  end  line  from start  from end"));
+        assert_eq!(table.lines().nth(3), Some("soil           50   52     4          98       100"));
+        Ok(())
+    }
+
+    #[test]
+    fn overlapping_seed_ranges() -> Result<()> {
+        let almanac = Almanac::try_from("seeds: 10 5 12 10 30 2 22 3 1 0\n\nseed-to-location map:\n100 20 5")?;
+        assert_eq!(almanac.seed_ranges()?, vec![10..25, 30..32]);
+        Ok(())
     }
 
     #[test]
-    fn example_2() {
-        assert_eq!(lowest_location_for_seed_ranges(EXAMPLE), 46);
+    fn reachable_location_ranges() -> Result<()> {
+        let reachable = reachable_locations(EXAMPLE, Validation::Strict)?;
+        assert_eq!(reachable, vec![46..61, 82..85, 86..90, 94..99]);
+        let almanac = Almanac::try_from(EXAMPLE)?;
+        let mut brute_force = vec![];
+        for seed in almanac.seed_ranges()?.into_iter().flatten() {
+            brute_force.push(almanac.map("seed", "location", seed)?);
+        }
+        brute_force.sort_unstable();
+        brute_force.dedup();
+        assert_eq!(reachable.into_iter().flatten().collect::<Vec<u64>>(), brute_force);
+        let input = "seeds: 10 5 12 10\n\nseed-to-location map:\n100 20 5";
+        assert_eq!(reachable_locations(input, Validation::Lenient)?, vec![10..20, 100..102]);
+        Ok(())
     }
 
     #[test]
-    fn part_1() {
-        println!("Part 1: {}", lowest_location_for_individual_seeds(include_str!("../res/day05.txt")));
+    fn part_1() -> Result<()> {
+        println!("Part 1: {}", lowest_location_for_individual_seeds(include_str!("../res/day05.txt"), Validation::Strict)?);
+        Ok(())
     }
 
     #[test]
-    fn part_2() {
-        println!("Part 2: {}", lowest_location_for_seed_ranges(include_str!("../res/day05.txt")));
+    fn part_2() -> Result<()> {
+        println!("Part 2: {}", lowest_location_for_seed_ranges(include_str!("../res/day05.txt"), Validation::Strict)?);
+        Ok(())
     }
 }
@@ -1,41 +1,297 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use anyhow::{anyhow, Context, Result};
+
+/// How a boat responds to the button. The standard race gains 1 mm/ms of speed per ms held, with no speed cap,
+/// no delay before moving and no drag.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Physics {
+    /// Speed gained per ms the button is held.
+    pub acceleration: u128,
+    /// The fastest the boat can go, however long the button is held.
+    pub max_speed: Option<u128>,
+    /// Time after letting go of the button before the boat starts moving.
+    pub startup_delay: u128,
+    /// Speed lost for every ms the boat travels.
+    pub drag: u128,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics { acceleration: 1, max_speed: None, startup_delay: 0, drag: 0 }
+    }
+}
+
+impl Physics {
+    /// Without a speed cap or drag, distance is `acceleration * press * (time - delay - press)`, which can be solved
+    /// directly.
+    fn is_quadratic(&self) -> bool {
+        self.max_speed.is_none() && self.drag == 0
+    }
+
+    /// The distance travelled for a press, or `None` if it's too far to count.
+    fn distance(&self, time: u128, press: u128) -> Option<u128> {
+        let speed = match (self.acceleration.checked_mul(press), self.max_speed) {
+            (Some(speed), max_speed) => max_speed.map_or(speed, |max| speed.min(max)),
+            (None, Some(max)) => max,
+            (None, None) => return None,
+        };
+        let travel = time.saturating_sub(press).saturating_sub(self.startup_delay);
+        if self.drag == 0 {
+            return speed.checked_mul(travel);
+        }
+        let moving = travel.min(speed.div_ceil(self.drag));
+        speed.checked_mul(moving)?.checked_sub(self.drag.checked_mul(moving.checked_mul(moving.saturating_sub(1))? / 2)?)
+    }
+}
+
+#[derive(Debug)]
 struct Event {
-    time: u64,
-    record: u64,
+    time: u128,
+    record: u128,
 }
 
 impl Event {
-    fn distance(&self, press: u64) -> u64 {
-        press * (self.time - press)
+    /// The distance travelled for a press, or `None` if it's too far to count.
+    fn distance(&self, press: u128) -> Option<u128> {
+        press.checked_mul(self.time - press)
+    }
+
+    fn wins(&self, press: u128) -> bool {
+        self.distance(press).is_none_or(|distance| distance > self.record)
+    }
+
+    /// The first press that beats the record. Where `time` squared fits this comes from the roots of
+    /// `press * (time - press) = record`, nudged to correct for the integer square root rounding down. Longer races
+    /// binary search the first half of the presses instead, where distance only grows.
+    fn first_winning_press(&self) -> Option<u128> {
+        let half = self.time / 2;
+        let mut press = match self.time.checked_mul(self.time) {
+            Some(squared) => (self.time - squared.checked_sub(self.record.checked_mul(4)?)?.isqrt()) / 2,
+            None => {
+                let (mut low, mut high) = (0, half + 1);
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    if self.wins(mid) { high = mid } else { low = mid + 1 }
+                }
+                low
+            }
+        };
+        while press > 0 && self.wins(press - 1) {
+            press -= 1;
+        }
+        while press <= half && !self.wins(press) {
+            press += 1;
+        }
+        (press <= half).then_some(press)
+    }
+
+    /// Presses are symmetric around half the time, so the last winning press mirrors the first.
+    fn winning_presses(&self) -> u128 {
+        self.first_winning_press().map_or(0, |first| self.time - first - first + 1)
+    }
+
+    /// Quadratic physics reduce to the standard race over the time left after the delay, with the record scaled down
+    /// by the acceleration. Anything else is searched numerically.
+    fn winning_presses_with(&self, physics: &Physics) -> u128 {
+        if !physics.is_quadratic() {
+            return self.winning_presses_by_search(physics);
+        }
+        if physics.acceleration == 0 || self.time <= physics.startup_delay {
+            return 0;
+        }
+        Event { time: self.time - physics.startup_delay, record: self.record / physics.acceleration }.winning_presses()
+    }
+
+    /// Distance still rises to a peak and falls away again, so binary search for the peak and then for where each
+    /// side of it crosses the record.
+    fn winning_presses_by_search(&self, physics: &Physics) -> u128 {
+        let distance = |press| physics.distance(self.time, press).unwrap_or(u128::MAX);
+        let wins = |press| physics.distance(self.time, press).is_none_or(|distance| distance > self.record);
+        let search = |mut low: u128, mut high: u128, past: &dyn Fn(u128) -> bool| {
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if past(mid) { high = mid } else { low = mid + 1 }
+            }
+            low
+        };
+        let peak = search(0, self.time, &|press| distance(press) >= distance(press + 1));
+        if !wins(peak) {
+            return 0;
+        }
+        let first = search(0, peak, &wins);
+        // Searching up to the press after `time` would overflow on the longest races, so check that one directly
+        let last = if wins(self.time) { self.time } else { search(peak, self.time, &|press| !wins(press)) - 1 };
+        last - first + 1
     }
+}
+
+/// The details of a race under the standard physics.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RaceAnalysis {
+    pub name: String,
+    pub time: u128,
+    pub record: u128,
+    /// The first and last presses that beat the record, if any do.
+    pub winning_presses: Option<RangeInclusive<u128>>,
+    /// The press going furthest. Half way through the race is always best.
+    pub best_press: u128,
+    /// How far the best press goes, or `None` if it's too far to count.
+    pub best_distance: Option<u128>,
+    /// How far the best press beats the record by, or `None` if no press wins or the best is too far to count.
+    pub margin: Option<u128>,
+    /// How much the record can grow while still being beatable, or `None` if it can't be beaten now.
+    pub record_headroom: Option<u128>,
+}
 
-    fn distances(&self) -> Vec<u64> {
-        (0..=self.time).map(|press| self.distance(press)).collect()
+impl RaceAnalysis {
+    fn new(name: &str, event: &Event) -> Self {
+        let best_press = event.time / 2;
+        let best_distance = event.distance(best_press);
+        let winning_presses = event.first_winning_press().map(|first| first..=event.time - first);
+        let margin = match winning_presses {
+            Some(_) => best_distance.map(|best| best - event.record),
+            None => None,
+        };
+        RaceAnalysis {
+            name: name.to_string(),
+            time: event.time,
+            record: event.record,
+            winning_presses,
+            best_press,
+            best_distance,
+            margin,
+            record_headroom: margin.map(|margin| margin - 1),
+        }
     }
 }
 
-fn load_events(input: &str) -> Vec<Event> {
-    let (times_str, records_str) = input.split_once('\n').unwrap();
-    let times = times_str.split_whitespace().skip(1).map(|time| time.parse::<u64>().unwrap());
-    let records = records_str.split_whitespace().skip(1).map(|record| record.parse::<u64>().unwrap());
-    times.zip(records).map(|(time, record)| Event { time, record }).collect()
+pub struct RaceReport(pub Vec<RaceAnalysis>);
+
+impl fmt::Display for RaceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn cell(value: Option<u128>) -> String {
+            value.map_or("-".to_string(), |v| v.to_string())
+        }
+
+        let heading = ["race", "time", "record", "first win", "last win", "wins", "best press", "best distance", "margin", "headroom"];
+        let mut rows = vec![heading.map(str::to_string).to_vec()];
+        for race in &self.0 {
+            let wins = race.winning_presses.as_ref().map_or(0, |presses| presses.end() - presses.start() + 1);
+            rows.push(vec![
+                race.name.clone(),
+                race.time.to_string(),
+                race.record.to_string(),
+                cell(race.winning_presses.as_ref().map(|presses| *presses.start())),
+                cell(race.winning_presses.as_ref().map(|presses| *presses.end())),
+                wins.to_string(),
+                race.best_press.to_string(),
+                cell(race.best_distance),
+                cell(race.margin),
+                cell(race.record_headroom),
+            ]);
+        }
+        let widths: Vec<usize> = (0..heading.len()).map(|c| rows.iter().map(|row| row[c].len()).max().unwrap_or(0)).collect();
+        for row in rows {
+            let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, &width)| format!("{:>width$}", cell)).collect();
+            writeln!(f, "{}", cells.join("  "))?;
+        }
+        Ok(())
+    }
 }
 
-fn load_event(input: &str) -> Event {
-    let (time_str, record_str) = input.split_once('\n').unwrap();
-    let time = time_str.replace(' ', "").split_once(':').unwrap().1.parse::<u64>().unwrap();
-    let record = record_str.replace(' ', "").split_once(':').unwrap().1.parse::<u64>().unwrap();
-    Event { time, record }
+fn parse_value(value: &str) -> Result<u128> {
+    value.parse::<u128>().with_context(|| format!("Couldn't parse race value ({})", value))
 }
 
-pub fn number_of_winning_combos_part_1(input: &str) -> u64 {
-    load_events(input).iter().map(|event| event.distances().into_iter().filter(|&d| d > event.record).count() as u64).product()
+/// How the columns of a race sheet are read into races.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// Every column is a race of its own.
+    Columns,
+    /// All the columns are one race, with their digits run together.
+    Concatenated,
+    /// Runs of neighbouring columns of the given sizes are each one race, with their digits run together.
+    Groups(Vec<usize>),
 }
 
-pub fn number_of_winning_combos_part_2(input: &str) -> u64 {
-    let event = load_event(input);
-    let first_winning = (0..=event.time).find(|&press| event.distance(press) > event.record).unwrap();
-    let last_winning = (0..=event.time).rfind(|&press| event.distance(press) > event.record).unwrap();
-    last_winning - first_winning + 1
+impl Layout {
+    fn group_sizes(&self, columns: usize) -> Result<Vec<usize>> {
+        match self {
+            Layout::Columns => Ok(vec![1; columns]),
+            Layout::Concatenated => Ok(vec![columns]),
+            Layout::Groups(sizes) => {
+                if sizes.iter().sum::<usize>() != columns || sizes.contains(&0) {
+                    Err(anyhow!("column groups {:?} don't cover {} columns", sizes, columns))?;
+                }
+                Ok(sizes.clone())
+            }
+        }
+    }
+}
+
+/// Reads a race sheet of labelled rows. "Time" and "Distance" rows are required, and an optional "Name" row names
+/// each column, otherwise columns are numbered from 1. Any other labelled rows are ignored.
+fn load_events(input: &str, layout: &Layout) -> Result<Vec<(String, Event)>> {
+    let mut rows: Vec<(&str, Vec<&str>)> = vec![];
+    for (i, line) in input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let (label, values) = line.split_once(':').with_context(|| format!("line {}: row missing ':' ({})", i + 1, line))?;
+        let label = label.trim();
+        if rows.iter().any(|(l, _)| *l == label) {
+            Err(anyhow!("line {}: more than one {} row", i + 1, label))?;
+        }
+        rows.push((label, values.split_whitespace().collect()));
+    }
+    let row = |label: &str| rows.iter().find(|(l, _)| *l == label).map(|(_, values)| values);
+    let times = row("Time").with_context(|| "missing Time row")?;
+    let records = row("Distance").with_context(|| "missing Distance row")?;
+    let numbers: Vec<String> = (1..=times.len()).map(|n| n.to_string()).collect();
+    let names: Vec<&str> = row("Name").map_or_else(|| numbers.iter().map(String::as_str).collect(), |names| names.clone());
+    for (label, values) in [("Distance", records), ("Name", &names)] {
+        if values.len() != times.len() {
+            Err(anyhow!("{} row has {} columns but Time row has {}", label, values.len(), times.len()))?;
+        }
+    }
+
+    let mut events = vec![];
+    let mut column = 0;
+    for size in layout.group_sizes(times.len())? {
+        let columns = column..column + size;
+        events.push((names[columns.clone()].join("+"), Event {
+            time: parse_value(&times[columns.clone()].concat())?,
+            record: parse_value(&records[columns].concat())?,
+        }));
+        column += size;
+    }
+    Ok(events)
+}
+
+pub fn number_of_winning_combos(input: &str, layout: &Layout, physics: &Physics) -> Result<u128> {
+    load_events(input, layout)?.iter()
+        .map(|(_, event)| event.winning_presses_with(physics))
+        .try_fold(1u128, |product, presses| product.checked_mul(presses))
+        .with_context(|| "too many winning combos to count")
+}
+
+pub fn number_of_winning_combos_part_1(input: &str, physics: &Physics) -> Result<u128> {
+    number_of_winning_combos(input, &Layout::Columns, physics)
+}
+
+pub fn number_of_winning_combos_part_2(input: &str, physics: &Physics) -> Result<u128> {
+    number_of_winning_combos(input, &Layout::Concatenated, physics)
+}
+
+pub fn race_report(input: &str, layout: &Layout) -> Result<RaceReport> {
+    Ok(RaceReport(load_events(input, layout)?.iter().map(|(name, event)| RaceAnalysis::new(name, event)).collect()))
+}
+
+pub fn race_report_part_1(input: &str) -> Result<RaceReport> {
+    race_report(input, &Layout::Columns)
+}
+
+pub fn race_report_part_2(input: &str) -> Result<RaceReport> {
+    race_report(input, &Layout::Concatenated)
 }
 
 #[cfg(test)]
@@ -44,23 +300,184 @@ mod tests {
 
     const EXAMPLE: &str = "Time:      7  15   30\nDistance:  9  40  200";
 
+    fn brute_force_winning_presses(event: &Event) -> u128 {
+        (0..=event.time).filter(|&press| event.wins(press)).count() as u128
+    }
+
+    fn scanned_winning_presses(event: &Event, physics: &Physics) -> u128 {
+        (0..=event.time)
+            .filter(|&press| physics.distance(event.time, press).is_none_or(|distance| distance > event.record))
+            .count() as u128
+    }
+
+    #[test]
+    fn example_1() -> Result<()> {
+        assert_eq!(number_of_winning_combos_part_1(EXAMPLE, &Physics::default())?, 288);
+        Ok(())
+    }
+
+    #[test]
+    fn example_2() -> Result<()> {
+        assert_eq!(number_of_winning_combos_part_2(EXAMPLE, &Physics::default())?, 71503);
+        Ok(())
+    }
+
+    #[test]
+    fn closed_form_matches_brute_force() {
+        let mut seed = 7u64;
+        for _ in 0..2000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let time = ((seed >> 33) % 300) as u128;
+            let max_distance = (time / 2) * (time - time / 2);
+            let record = ((seed >> 13) as u128) % (max_distance + 3);
+            let event = Event { time, record };
+            assert_eq!(event.winning_presses(), brute_force_winning_presses(&event), "time {}, record {}", time, record);
+        }
+        for time in 0..60 {
+            for record in 0..=(time / 2) * (time - time / 2) + 1 {
+                let event = Event { time, record };
+                assert_eq!(event.winning_presses(), brute_force_winning_presses(&event), "time {}, record {}", time, record);
+            }
+        }
+    }
+
+    #[test]
+    fn huge_times() {
+        let max = u64::MAX as u128;
+        assert_eq!(Event { time: max, record: 0 }.winning_presses(), max - 1);
+        assert_eq!(Event { time: max, record: max }.winning_presses(), max - 3);
+        let event = Event { time: max - 1, record: max };
+        let first = event.first_winning_press().unwrap();
+        assert!(event.wins(first) && !event.wins(first - 1));
+        assert_eq!(event.winning_presses(), max - 1 - 2 * first + 1);
+    }
+
+    #[test]
+    fn beyond_u64() -> Result<()> {
+        let input = "Time:      18446744073709551615 0\nDistance:  9  40";
+        assert_eq!(number_of_winning_combos_part_2(input, &Physics::default())?, 184467440737095516150 - 1);
+        assert_eq!(Event { time: u128::MAX, record: u128::MAX }.winning_presses(), u128::MAX - 3);
+        let event = Event { time: u128::MAX, record: u128::MAX / 3 };
+        let first = event.first_winning_press().unwrap();
+        assert!(event.wins(first) && !event.wins(first - 1));
+        assert!(number_of_winning_combos_part_2("Time: 999999999999999999999999999999999999999\nDistance: 1", &Physics::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn quadratic_physics() {
+        let physics = [
+            Physics { acceleration: 3, ..Physics::default() },
+            Physics { startup_delay: 4, ..Physics::default() },
+            Physics { acceleration: 2, startup_delay: 1, ..Physics::default() },
+            Physics { acceleration: 0, ..Physics::default() },
+        ];
+        for physics in physics {
+            for time in 0..40 {
+                for record in (0..200).step_by(7) {
+                    let event = Event { time, record };
+                    assert_eq!(event.winning_presses_with(&physics), scanned_winning_presses(&event, &physics),
+                               "{:?}, time {}, record {}", physics, time, record);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn capped_and_dragged_physics() -> Result<()> {
+        let capped = Physics { max_speed: Some(3), ..Physics::default() };
+        assert_eq!(capped.distance(7, 2), Some(10));
+        assert_eq!(capped.distance(7, 4), Some(9));
+        assert_eq!(number_of_winning_combos_part_1(EXAMPLE, &capped)?, 0);
+        assert_eq!(Event { time: 7, record: 8 }.winning_presses_with(&capped), 3);
+        let fast = Physics { acceleration: u128::MAX, max_speed: Some(1), ..Physics::default() };
+        assert_eq!(fast.distance(3, 2), Some(1));
+        assert_eq!(Event { time: 3, record: 100 }.winning_presses_with(&fast), 0);
+        let capped_max = Event { time: u128::MAX, record: 10 };
+        assert_eq!(capped_max.winning_presses_with(&Physics { max_speed: Some(3), ..Physics::default() }), u128::MAX - 4);
+        // with drag 1 a press of p coasts p(p+1)/2, so presses from 5 up to one before the end win
+        assert_eq!(capped_max.winning_presses_with(&Physics { drag: 1, ..Physics::default() }), u128::MAX - 5);
+        assert_eq!(number_of_winning_combos_part_1(&format!("Time: {}\nDistance: 10", u128::MAX), &Physics { max_speed: Some(3), ..Physics::default() })?, u128::MAX - 4);
+        let long = Physics { max_speed: Some(10), ..Physics::default() };
+        assert_eq!(Event { time: 10u128.pow(30), record: 10 }.winning_presses_with(&long), 10u128.pow(30) - 2);
+        let dragged = Physics { drag: 2, ..Physics::default() };
+        assert_eq!(dragged.distance(7, 4), Some(4 + 2));
+        assert_eq!(dragged.distance(7, 5), Some(5 + 3));
+        assert_eq!(dragged.distance(30, 5), Some(5 + 3 + 1));
+        assert_eq!(Event { time: 7, record: 5 }.winning_presses_with(&dragged), 3);
+        for physics in [capped, dragged, Physics { max_speed: Some(4), drag: 1, startup_delay: 2, acceleration: 2 }] {
+            for (time, record) in [(0, 0), (1, 0), (7, 0), (7, 5), (30, 20), (30, 200), (45, 31)] {
+                let event = Event { time, record };
+                assert_eq!(event.winning_presses_with(&physics), scanned_winning_presses(&event, &physics), "{:?} {} {}", physics, time, record);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn race_analysis() -> Result<()> {
+        let report = race_report_part_1(EXAMPLE)?;
+        assert_eq!(report.0[0], RaceAnalysis {
+            name: "1".to_string(),
+            time: 7,
+            record: 9,
+            winning_presses: Some(2..=5),
+            best_press: 3,
+            best_distance: Some(12),
+            margin: Some(3),
+            record_headroom: Some(2),
+        });
+        assert_eq!(report.0[2].winning_presses, Some(11..=19));
+        assert_eq!(report.0[2].record_headroom, Some(24));
+        let lost = RaceAnalysis::new("lost", &Event { time: 4, record: 4 });
+        assert_eq!((lost.winning_presses, lost.margin, lost.record_headroom), (None, None, None));
+        let close = RaceAnalysis::new("close", &Event { time: 4, record: 3 });
+        assert_eq!((close.winning_presses, close.margin, close.record_headroom), (Some(2..=2), Some(1), Some(0)));
+        let table = report.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "race  time  record  first win  last win  wins  best press  best distance  margin  headroom");
+        assert_eq!(lines[1], "   1     7       9          2         5     4           3             12       3         2");
+        assert_eq!(race_report_part_2(EXAMPLE)?.0[0].winning_presses, Some(14..=71516));
+        Ok(())
+    }
+
     #[test]
-    fn example_1() {
-        assert_eq!(number_of_winning_combos_part_1(EXAMPLE), 288);
+    fn layouts() -> Result<()> {
+        let input = "Name:  a   b    c\nTime:      7  15   30\nDistance:  9  40  200\nWeather: wet dry wet";
+        let events = load_events(input, &Layout::Groups(vec![1, 2]))?;
+        assert_eq!(events.iter().map(|(name, event)| (name.as_str(), event.time, event.record)).collect::<Vec<_>>(),
+                   vec![("a", 7, 9), ("b+c", 1530, 40200)]);
+        assert_eq!(number_of_winning_combos(input, &Layout::Columns, &Physics::default())?, 288);
+        assert_eq!(number_of_winning_combos(input, &Layout::Concatenated, &Physics::default())?, 71503);
+        assert_eq!(race_report(input, &Layout::Concatenated)?.0[0].name, "a+b+c");
+        assert_eq!(number_of_winning_combos(&format!("{}\n", EXAMPLE.replace('\n', "\r\n")), &Layout::Columns, &Physics::default())?, 288);
+        Ok(())
     }
 
     #[test]
-    fn example_2() {
-        assert_eq!(number_of_winning_combos_part_2(EXAMPLE), 71503);
+    fn malformed_sheets() {
+        let error = load_events("Time: 7 15 30\nDistance: 9 40", &Layout::Columns).unwrap_err();
+        assert_eq!(error.to_string(), "Distance row has 2 columns but Time row has 3");
+        let error = load_events("Name: a b\nTime: 7 15 30\nDistance: 9 40 200", &Layout::Columns).unwrap_err();
+        assert_eq!(error.to_string(), "Name row has 2 columns but Time row has 3");
+        let error = load_events(EXAMPLE, &Layout::Groups(vec![1, 1])).unwrap_err();
+        assert_eq!(error.to_string(), "column groups [1, 1] don't cover 3 columns");
+        let error = load_events("Time: 7\nTime: 8\nDistance: 9", &Layout::Columns).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: more than one Time row");
+        assert_eq!(load_events("Time: 7", &Layout::Columns).unwrap_err().to_string(), "missing Distance row");
     }
 
     #[test]
-    fn part_1() {
-        println!("Part 1: {}", number_of_winning_combos_part_1(include_str!("../res/day06.txt")));
+    fn part_1() -> Result<()> {
+        println!("Part 1: {}", number_of_winning_combos_part_1(include_str!("../res/day06.txt"), &Physics::default())?);
+        print!("{}", race_report_part_1(include_str!("../res/day06.txt"))?);
+        Ok(())
     }
 
     #[test]
-    fn part_2() {
-        println!("Part 2: {}", number_of_winning_combos_part_2(include_str!("../res/day06.txt")));
+    fn part_2() -> Result<()> {
+        println!("Part 2: {}", number_of_winning_combos_part_2(include_str!("../res/day06.txt"), &Physics::default())?);
+        print!("{}", race_report_part_2(include_str!("../res/day06.txt"))?);
+        Ok(())
     }
 }
@@ -1,121 +1,352 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashMap;
+use std::fmt;
 
-use crate::day07::WinType::{FiveOfAKind, FourOfAKind, FullHouse, HighCard, OnePair, ThreeOfAKind, TwoPair};
+use anyhow::{anyhow, Context, Result};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
-enum Card {
-    Ace = 14,
-    King = 13,
-    Queen = 12,
-    Jack = 11,
-    Ten = 10,
-    Nine = 9,
-    Eight = 8,
-    Seven = 7,
-    Six = 6,
-    Five = 5,
-    Four = 4,
-    Three = 3,
-    Two = 2,
-    Joker = 1,
-}
-
-impl From<(char, bool)> for Card {
-    fn from(value: (char, bool)) -> Self {
-        let (card, j_is_joker) = value;
-        match card {
-            'A' => Card::Ace,
-            'K' => Card::King,
-            'Q' => Card::Queen,
-            'J' => if j_is_joker { Card::Joker } else { Card::Jack },
-            'T' => Card::Ten,
-            '9' => Card::Nine,
-            '8' => Card::Eight,
-            '7' => Card::Seven,
-            '6' => Card::Six,
-            '5' => Card::Five,
-            '4' => Card::Four,
-            '3' => Card::Three,
-            '2' => Card::Two,
-            other => panic!("Don't know what a '{}' card is", other),
+use crate::day07::WinType::{Flush, FiveOfAKind, FourOfAKind, FullHouse, HighCard, OnePair, Straight, StraightFlush, ThreeOfAKind, TwoPair};
+
+/// Where wild cards rank when hands of the same type are compared card by card.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WildRank {
+    /// Wild cards keep their place in the ranking.
+    Natural,
+    /// Wild cards rank below every other card.
+    Lowest,
+}
+
+/// Which game the hands are played under.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Game {
+    /// Hands of any length, typed by matching cards and tied by comparing cards position by position.
+    CamelCards,
+    /// Five suited cards such as "AsKd9h9c2s", with straights and flushes, tied by comparing rank groups.
+    Poker,
+}
+
+const SUITS: &str = "CDHS";
+
+/// The cards of a Camel Cards variant: its alphabet in rank order from lowest to highest, which cards are wild,
+/// and how wild cards rank.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rules {
+    ranking: Vec<char>,
+    wilds: Vec<char>,
+    wild_rank: WildRank,
+    game: Game,
+}
+
+impl Rules {
+    pub fn new(ranking: &str, wilds: &str, wild_rank: WildRank) -> Result<Self> {
+        let ranking: Vec<char> = ranking.chars().collect();
+        let wilds: Vec<char> = wilds.chars().collect();
+        if let Some(duplicate) = ranking.iter().enumerate().find(|(i, c)| ranking[..*i].contains(c)) {
+            Err(anyhow!("card '{}' appears more than once in the ranking", duplicate.1))?;
+        }
+        if let Some(unknown) = wilds.iter().find(|c| !ranking.contains(c)) {
+            Err(anyhow!("wild card '{}' isn't in the ranking", unknown))?;
+        }
+        Ok(Rules { ranking, wilds, wild_rank, game: Game::CamelCards })
+    }
+
+    pub fn with_game(self, game: Game) -> Result<Self> {
+        if game == Game::Poker && !self.wilds.is_empty() {
+            Err(anyhow!("poker is played without wild cards"))?;
+        }
+        Ok(Rules { game, ..self })
+    }
+
+    /// The standard game, with no wild cards.
+    pub fn standard() -> Self {
+        Rules::new("23456789TJQKA", "", WildRank::Natural).unwrap()
+    }
+
+    /// The standard game with 'J' as a joker, wild and ranking below every other card.
+    pub fn jokers() -> Self {
+        Rules::new("23456789TJQKA", "J", WildRank::Lowest).unwrap()
+    }
+
+    /// Five card poker, aces high (and low in the A-2-3-4-5 straight).
+    pub fn poker() -> Self {
+        Rules::standard().with_game(Game::Poker).unwrap()
+    }
+
+    fn cards(&self, text: &str) -> Result<Vec<Card>> {
+        match self.game {
+            Game::CamelCards => text.chars().map(|c| self.card(c)).collect(),
+            Game::Poker => {
+                let mut chars = text.chars();
+                let mut cards = Vec::new();
+                while let Some(label) = chars.next() {
+                    let suit = chars.next().with_context(|| format!("card '{}' is missing its suit", label))?.to_ascii_uppercase();
+                    if !SUITS.contains(suit) {
+                        Err(anyhow!("Don't know what suit '{}' is", suit))?;
+                    }
+                    let card = Card { suit: Some(suit), ..self.card(label)? };
+                    if cards.contains(&card) {
+                        Err(anyhow!("card '{}{}' appears more than once", label, suit))?;
+                    }
+                    cards.push(card);
+                }
+                if cards.len() != 5 {
+                    Err(anyhow!("poker hands have 5 cards, not {}", cards.len()))?;
+                }
+                Ok(cards)
+            }
         }
     }
+
+    fn card(&self, label: char) -> Result<Card> {
+        let position = self.ranking.iter().position(|&c| c == label).with_context(|| format!("Don't know what a '{}' card is", label))?;
+        let wild = self.wilds.contains(&label);
+        let rank = match (wild, self.wild_rank) {
+            (true, WildRank::Lowest) => 0,
+            _ => position + 1,
+        };
+        Ok(Card { rank, wild, label, suit: None })
+    }
+}
+
+/// A card, ordered by its rank under the rules it was dealt with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+struct Card {
+    rank: usize,
+    wild: bool,
+    label: char,
+    suit: Option<char>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
-enum WinType {
-    FiveOfAKind = 6,
-    FourOfAKind = 5,
-    FullHouse = 4,
+pub enum WinType {
+    FiveOfAKind = 9,
+    StraightFlush = 8,
+    FourOfAKind = 7,
+    FullHouse = 6,
+    Flush = 5,
+    Straight = 4,
     ThreeOfAKind = 3,
     TwoPair = 2,
     OnePair = 1,
     HighCard = 0,
 }
 
+impl WinType {
+    /// Names a hand by the sizes of its two largest groups of matching cards, which for five cards gives every type.
+    fn from_signature(signature: &[usize]) -> WinType {
+        match (signature.first().copied().unwrap_or(0), signature.get(1).copied().unwrap_or(0)) {
+            (5.., _) => FiveOfAKind,
+            (4, _) => FourOfAKind,
+            (3, 2..) => FullHouse,
+            (3, _) => ThreeOfAKind,
+            (2, 2) => TwoPair,
+            (2, _) => OnePair,
+            _ => HighCard,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Hand {
-    cards: [Card; 5],
+    cards: Vec<Card>,
     bid: i64,
+    signature: Vec<usize>,
     win_type: WinType,
+    tiebreak: Vec<usize>,
 }
 
 impl Hand {
-    fn new(cards: [Card; 5], bid: i64) -> Self {
+    fn new(cards: Vec<Card>, bid: i64, rules: &Rules) -> Self {
+        let signature = Self::signature(&cards);
+        let (win_type, tiebreak) = match rules.game {
+            Game::CamelCards => (WinType::from_signature(&signature), cards.iter().map(|card| card.rank).collect()),
+            Game::Poker => Self::poker_type(&cards, &signature, rules.ranking.len()),
+        };
         Hand {
             cards,
             bid,
-            win_type: Self::determine_win_type(&cards),
+            signature,
+            win_type,
+            tiebreak,
         }
     }
 
-    fn determine_win_type(cards: &[Card; 5]) -> WinType {
-        let mut counts = HashMap::new();
+    /// Types a poker hand and gives the ranks that break ties within the type: the ranks of the groups, largest
+    /// group first then highest rank first, or just the top card of a straight.
+    fn poker_type(cards: &[Card], signature: &[usize], ace: usize) -> (WinType, Vec<usize>) {
+        let mut groups: Vec<(usize, usize)> = Vec::new();
         for card in cards {
-            *counts.entry(*card).or_insert_with(|| 0u8) += 1;
-        }
-        let wilds = counts.remove(&Card::Joker).unwrap_or(0);
-        match counts.len() {
-            0 | 1 => FiveOfAKind,
-            2 => {
-                if counts.iter().any(|(_, &count)| (count + wilds) == 4)  {
-                    FourOfAKind
-                } else {
-                    FullHouse
-                }
+            match groups.iter_mut().find(|(_, rank)| *rank == card.rank) {
+                Some((count, _)) => *count += 1,
+                None => groups.push((1, card.rank)),
             }
-            3 => {
-                if counts.iter().any(|(_, &count)| (count + wilds) == 3) {
-                    ThreeOfAKind
-                } else {
-                    TwoPair
-                }
+        }
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        let mut tiebreak: Vec<usize> = groups.iter().map(|&(_, rank)| rank).collect();
+
+        let flush = cards.windows(2).all(|pair| pair[0].suit == pair[1].suit);
+        let straight = match tiebreak[..] {
+            [high, .., low] if groups.len() == 5 && high - low == 4 => true,
+            [high, 4, 3, 2, 1] if high == ace => {
+                tiebreak = vec![4, 3, 2, 1, 0];
+                true
             }
-            4 => OnePair,
-            5 => HighCard,
-            _ => panic!("More cards than expected! {:?}", cards),
+            _ => false,
+        };
+        if straight {
+            tiebreak.truncate(1);
+        }
+
+        let win_type = match (straight, flush) {
+            (true, true) => StraightFlush,
+            (true, false) => Straight,
+            (false, true) => Flush,
+            (false, false) => WinType::from_signature(signature),
+        };
+        (win_type, tiebreak)
+    }
+
+    /// The sizes of the groups of matching cards, largest first, with wild cards joining the largest group. Hands of
+    /// any size compare by signature, and for five cards this orders them the same as their `WinType`.
+    fn signature(cards: &[Card]) -> Vec<usize> {
+        let mut counts = HashMap::new();
+        for card in cards.iter().filter(|card| !card.wild) {
+            *counts.entry(card.label).or_insert_with(|| 0) += 1;
+        }
+        let wilds = cards.iter().filter(|card| card.wild).count();
+        let mut signature: Vec<usize> = counts.into_values().collect();
+        signature.sort_unstable_by(|a, b| b.cmp(a));
+        match signature.first_mut() {
+            Some(largest) => *largest += wilds,
+            None if wilds > 0 => signature.push(wilds),
+            None => {}
         }
+        signature
     }
 }
 
-pub fn total_winnings(input: &str, jokers_wild: bool) -> i64 {
-    let mut hands = load_hands(input, jokers_wild);
-    hands.sort_unstable_by(|a, b| match a.win_type.cmp(&b.win_type) {
+/// What the wild cards in a hand were best played as.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Substitution {
+    pub hand: String,
+    pub bid: i64,
+    /// The hand with each wild card replaced by the card it plays as.
+    pub substituted: String,
+    pub win_type: WinType,
+    pub reason: Reason,
+}
+
+/// Why a substitution was chosen over the others.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Reason {
+    NoWilds,
+    Only,
+    /// Nothing else makes as strong a hand; `alternative` is the next strongest, with one wild card played differently.
+    Beats { alternative: String, win_type: WinType },
+    /// Other substitutions make an equally strong hand, and the one with the highest cards was chosen.
+    Ties { others: usize },
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::NoWilds => write!(f, "no wild cards to substitute"),
+            Reason::Only => write!(f, "the only possible substitution"),
+            Reason::Beats { alternative, win_type } => write!(f, "beats the next best, {} ({:?})", alternative, win_type),
+            Reason::Ties { others } => write!(f, "ties with {} other substitutions, played as the highest cards", others),
+        }
+    }
+}
+
+/// For each hand, in input order, reports the strongest hand its wild cards can make and why.
+pub fn explain_wilds(input: &str, rules: &Rules) -> Result<Vec<Substitution>> {
+    let candidates = rules.ranking.iter()
+        .filter(|label| !rules.wilds.contains(label))
+        .map(|&label| rules.card(label))
+        .collect::<Result<Vec<Card>>>()?;
+    Ok(load_hands(input, rules)?.iter().map(|hand| explain_hand(hand, &candidates)).collect())
+}
+
+fn explain_hand(hand: &Hand, candidates: &[Card]) -> Substitution {
+    let wilds: Vec<usize> = hand.cards.iter().enumerate().filter(|(_, card)| card.wild).map(|(i, _)| i).collect();
+    let explain = |substituted: &[Card], win_type, reason| Substitution {
+        hand: labels(&hand.cards),
+        bid: hand.bid,
+        substituted: labels(substituted),
+        win_type,
+        reason,
+    };
+    if wilds.is_empty() || candidates.is_empty() {
+        return explain(&hand.cards, hand.win_type, Reason::NoWilds);
+    }
+
+    // Wild cards always do best joining the largest group, and the highest ranked of those if several tie
+    let mut groups: Vec<(usize, Card)> = Vec::new();
+    for &card in hand.cards.iter().filter(|card| !card.wild) {
+        match groups.iter_mut().find(|(_, other)| other.label == card.label) {
+            Some((count, _)) => *count += 1,
+            None => groups.push((1, card)),
+        }
+    }
+    groups.sort_unstable_by_key(|&(count, card)| Reverse((count, card.rank)));
+    let highest = |except: Option<char>| candidates.iter().filter(|card| Some(card.label) != except).max_by_key(|card| card.rank).copied();
+    let best = groups.first().map(|&(_, card)| card).or_else(|| highest(None)).unwrap();
+    let play_as = |last: Card| -> (Vec<Card>, WinType) {
+        let mut cards = hand.cards.clone();
+        for (i, &position) in wilds.iter().enumerate() {
+            cards[position] = if i + 1 == wilds.len() { last } else { best };
+        }
+        let win_type = WinType::from_signature(&Hand::signature(&cards));
+        (cards, win_type)
+    };
+    let (cards, win_type) = play_as(best);
+
+    let tied = match groups.first() {
+        Some(&(largest, _)) => groups.iter().filter(|&&(count, _)| count == largest).count() - 1,
+        None => candidates.len() - 1,
+    };
+    let reason = if candidates.len() == 1 {
+        Reason::Only
+    } else if tied > 0 {
+        Reason::Ties { others: tied }
+    } else {
+        // The next best hand plays the last wild card with the next largest group, or as a new card if there is none
+        let next = groups.get(1).map(|&(_, card)| card).or_else(|| highest(Some(best.label))).unwrap();
+        let (alternative, alternative_type) = play_as(next);
+        Reason::Beats { alternative: labels(&alternative), win_type: alternative_type }
+    };
+    explain(&cards, win_type, reason)
+}
+
+fn labels(cards: &[Card]) -> String {
+    cards.iter().flat_map(|card| [Some(card.label), card.suit]).flatten().collect()
+}
+
+pub fn total_winnings(input: &str, rules: &Rules) -> Result<i64> {
+    let mut hands = load_hands(input, rules)?;
+    // Hands longer than five can differ within a type, e.g. [3, 3] beats [3, 2], so the signature settles those
+    hands.sort_unstable_by(|a, b| match (a.win_type, &a.signature).cmp(&(b.win_type, &b.signature)) {
         Ordering::Less => Ordering::Less,
         Ordering::Greater => Ordering::Greater,
         Ordering::Equal => {
-            a.cards.cmp(&b.cards)
+            a.tiebreak.cmp(&b.tiebreak)
         }
     });
-    hands.iter().enumerate().map(|(i, h)| (i as i64 + 1) * h.bid).sum()
+    Ok(hands.iter().enumerate().map(|(i, h)| (i as i64 + 1) * h.bid).sum())
 }
 
-fn load_hands(input: &str, jokers_wild: bool) -> Vec<Hand> {
+fn load_hands(input: &str, rules: &Rules) -> Result<Vec<Hand>> {
     input.lines()
-        .map(|line| line.split_once(' ').unwrap())
-        .map(|(cards, bid)| Hand::new(cards.chars().map(|c| Card::from((c, jokers_wild))).collect::<Vec<Card>>().try_into().unwrap(),
-                                      bid.parse::<i64>().unwrap()))
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let (cards, bid) = line.split_once(' ').with_context(|| format!("line {}: hand missing ' ' ({})", i + 1, line))?;
+            let cards = rules.cards(cards).with_context(|| format!("line {}", i + 1))?;
+            if cards.is_empty() {
+                Err(anyhow!("line {}: hand has no cards ({})", i + 1, line))?;
+            }
+            let bid = bid.trim().parse::<i64>().with_context(|| format!("line {}: Couldn't parse bid ({})", i + 1, bid))?;
+            Ok(Hand::new(cards, bid, rules))
+        })
         .collect()
 }
 
@@ -126,22 +357,160 @@ mod tests {
     const EXAMPLE: &str = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
 
     #[test]
-    fn example_1() {
-        assert_eq!(total_winnings(EXAMPLE, false), 6440);
+    fn example_1() -> Result<()> {
+        assert_eq!(total_winnings(EXAMPLE, &Rules::standard())?, 6440);
+        Ok(())
+    }
+
+    #[test]
+    fn example_2() -> Result<()> {
+        assert_eq!(total_winnings(EXAMPLE, &Rules::jokers())?, 5905);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_rules() -> Result<()> {
+        let aces_low = Rules::new("A23456789TJQK", "", WildRank::Natural)?;
+        assert_eq!(total_winnings("A2345 1\n23456 2", &aces_low)?, 5);
+        assert_eq!(total_winnings("A2345 1\n23456 2", &Rules::standard())?, 4);
+        let natural_jokers = Rules::new("23456789TJQKA", "J", WildRank::Natural)?;
+        assert_eq!(total_winnings("JJJJ2 1\n2222J 2", &natural_jokers)?, 4);
+        assert_eq!(total_winnings("JJJJ2 1\n2222J 2", &Rules::jokers())?, 5);
+        let two_wilds = Rules::new("23456789TJQKA", "2J", WildRank::Lowest)?;
+        assert_eq!(total_winnings("2J345 1\nTTT98 2", &two_wilds)?, 5);
+        let letters = Rules::new("abcde", "e", WildRank::Lowest)?;
+        assert_eq!(total_winnings("aabbe 10\naaabc 20", &letters)?, 40);
+        Ok(())
     }
 
     #[test]
-    fn example_2() {
-        assert_eq!(total_winnings(EXAMPLE, true), 5905);
+    fn long_ranking() -> Result<()> {
+        let ranking: String = (0..300).filter_map(char::from_u32).filter(|c| !c.is_whitespace()).collect();
+        let rules = Rules::new(&ranking, "", WildRank::Natural)?;
+        let (low, high) = (ranking.chars().nth(1).unwrap(), ranking.chars().last().unwrap());
+        // the first hand ranks second
+        assert_eq!(total_winnings(&format!("{high}{low} 1\n{low}{high} 2"), &rules)?, 2 + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_rules_and_hands() {
+        assert!(Rules::new("AKQA", "", WildRank::Natural).is_err());
+        assert!(Rules::new("AKQ", "J", WildRank::Natural).is_err());
+        let error = total_winnings("32T3K 765\n32X3K 1", &Rules::standard()).unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 2: Don't know what a 'X' card is");
+    }
+
+    #[test]
+    fn signatures() -> Result<()> {
+        let rules = Rules::jokers();
+        let signature = |cards: &str| -> Result<(Vec<usize>, WinType)> {
+            let hand = Hand::new(rules.cards(cards)?, 0, &rules);
+            Ok((hand.signature, hand.win_type))
+        };
+        assert_eq!(signature("32T3K")?, (vec![2, 1, 1, 1], OnePair));
+        assert_eq!(signature("KTJJT")?, (vec![4, 1], FourOfAKind));
+        assert_eq!(signature("JJJJJ")?, (vec![5], FiveOfAKind));
+        assert_eq!(signature("QQ")?, (vec![2], OnePair));
+        assert_eq!(signature("QQQKKK2")?, (vec![3, 3, 1], FullHouse));
+        assert_eq!(signature("QQQKKKJ")?, (vec![4, 3], FourOfAKind));
+        Ok(())
+    }
+
+    #[test]
+    fn variable_hand_sizes() -> Result<()> {
+        assert_eq!(total_winnings("AK 1\n22 2\nQQ 3", &Rules::standard())?, 1 + 2 * 2 + 3 * 3);
+        assert_eq!(total_winnings("AAAKKK 1\nAAAKK2 2\n222333 3", &Rules::standard())?, 2 + 3 * 2 + 3);
+        // the longer hand ranks second
+        assert_eq!(total_winnings("AAAKKK 1\nJJJ 2", &Rules::jokers())?, 2 + 2);
+        assert_eq!(total_winnings(&format!("{} 1\n{}J 2", "A".repeat(300), "K".repeat(300)), &Rules::jokers())?, 1 + 2 * 2);
+        assert!(total_winnings(" 1", &Rules::standard()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn poker() -> Result<()> {
+        let poker = Rules::poker();
+        let win_type = |cards: &str| -> Result<WinType> { Ok(Hand::new(poker.cards(cards)?, 0, &poker).win_type) };
+        assert_eq!(win_type("2h3d4s5c6h")?, Straight);
+        assert_eq!(win_type("Ah2d3s4c5h")?, Straight);
+        assert_eq!(win_type("KhQhJhTh9h")?, StraightFlush);
+        assert_eq!(win_type("Kh2h7h9h3h")?, Flush);
+        assert_eq!(win_type("KhKdKs2c2h")?, FullHouse);
+        assert_eq!(win_type("QhKhAh2h3h")?, Flush);
+        // straight beats three of a kind, and the A-2-3-4-5 straight is the lowest
+        assert_eq!(total_winnings("2H3D4S5C6H 1\nKHKDKS2C3D 2", &poker)?, 2 + 2);
+        assert_eq!(total_winnings("AH2D3S4C5H 1\n2H3D4S5C6H 2", &poker)?, 1 + 2 * 2);
+        // the pair decides before the cards in front of it, then the kickers in rank order
+        assert_eq!(total_winnings("AS2H2DKCQH 1\n3H3D4S5C7H 2", &poker)?, 1 + 2 * 2);
+        assert_eq!(total_winnings("KHKD2S3C9H 1\nKSKC8S7C6H 2", &poker)?, 2 + 2);
+        // the same hands as Camel Cards compare by position
+        assert_eq!(total_winnings("A22KQ 1\n33457 2", &Rules::standard())?, 2 + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_poker_hands() {
+        assert!(Rules::jokers().with_game(Game::Poker).is_err());
+        assert!(total_winnings("2H3D4S5C6 1", &Rules::poker()).is_err());
+        assert!(total_winnings("2H3D4S5C6X 1", &Rules::poker()).is_err());
+        let error = total_winnings("2H3D4S5C 1", &Rules::poker()).unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 1: poker hands have 5 cards, not 4");
+        let error = total_winnings("AhAdAhAsAc 1", &Rules::poker()).unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 1: card 'AH' appears more than once");
+    }
+
+    #[test]
+    fn wild_substitutions() -> Result<()> {
+        let explained = explain_wilds(EXAMPLE, &Rules::jokers())?;
+        assert_eq!(explained[0], Substitution {
+            hand: "32T3K".to_string(),
+            bid: 765,
+            substituted: "32T3K".to_string(),
+            win_type: OnePair,
+            reason: Reason::NoWilds,
+        });
+        assert_eq!(explained[1].substituted, "T5555");
+        assert_eq!(explained[1].win_type, FourOfAKind);
+        assert_eq!(explained[1].reason, Reason::Beats { alternative: "T55T5".to_string(), win_type: FullHouse });
+        assert_eq!(explained[3].substituted, "KTTTT");
+        assert_eq!(explained[3].reason.to_string(), "beats the next best, KTTKT (FullHouse)");
+        let long = explain_wilds(&format!("{} 1\n{}QQ2 2", "J".repeat(500), "J".repeat(500)), &Rules::jokers())?;
+        assert_eq!((long[0].win_type, &long[0].reason), (FiveOfAKind, &Reason::Ties { others: 11 }));
+        assert_eq!((long[1].win_type, long[1].substituted.len()), (FiveOfAKind, 503));
+        assert_eq!(long[1].reason, Reason::Beats { alternative: format!("{}2QQ2", "Q".repeat(499)), win_type: FiveOfAKind });
+
+        let ties = explain_wilds("JJJJJ 1\nKK22J 2", &Rules::jokers())?;
+        assert_eq!((ties[0].substituted.as_str(), ties[0].win_type), ("AAAAA", FiveOfAKind));
+        assert_eq!(ties[0].reason, Reason::Ties { others: 11 });
+        assert_eq!((ties[1].substituted.as_str(), ties[1].win_type), ("KK22K", FullHouse));
+        assert_eq!(ties[1].reason, Reason::Ties { others: 1 });
+
+        let only = Rules::new("AJ", "J", WildRank::Lowest)?;
+        assert_eq!(explain_wilds("AJ 1", &only)?[0].reason, Reason::Only);
+        assert_eq!(explain_wilds("AsKsQsJsTs 1", &Rules::poker())?[0].win_type, StraightFlush);
+        Ok(())
+    }
+
+    #[test]
+    fn wild_substitutions_agree_with_ranking() -> Result<()> {
+        let input = include_str!("../res/day07.txt");
+        let rules = Rules::jokers();
+        for (hand, explained) in load_hands(input, &rules)?.iter().zip(explain_wilds(input, &rules)?) {
+            assert_eq!(hand.win_type, explained.win_type, "{}", explained.hand);
+        }
+        Ok(())
     }
 
     #[test]
-    fn part_1() {
-        println!("Part 1: {}", total_winnings(include_str!("../res/day07.txt"), false));
+    fn part_1() -> Result<()> {
+        println!("Part 1: {}", total_winnings(include_str!("../res/day07.txt"), &Rules::standard())?);
+        Ok(())
     }
 
     #[test]
-    fn part_2() {
-        println!("Part 2: {}", total_winnings(include_str!("../res/day07.txt"), true));
+    fn part_2() -> Result<()> {
+        println!("Part 2: {}", total_winnings(include_str!("../res/day07.txt"), &Rules::jokers())?);
+        Ok(())
     }
 }